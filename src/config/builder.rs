@@ -1,17 +1,103 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_path_to_error::Segment;
 
-use super::env::EnvSource;
-use super::file::FileSource;
-use super::resolve::resolve_references;
-use super::source::{merge_at_path, ConfigSource};
+use super::case::{normalize_entry, normalize_path, KeyCase};
+use super::coerce::overflows_integer;
+use super::dotenv::DotenvSource;
+use super::env::{EnvOptions, EnvSource};
+#[cfg(feature = "schemars")]
+use super::example;
+use super::file::{parse, FileSource, Format};
+use super::finalize::Finalize;
+use super::glob::GlobSource;
+#[cfg(feature = "json")]
+use super::json::json_to_table;
+use super::merge::{check_type_conflict, merge_at_path, type_changes, ArrayMerge, MergeRules};
+use super::overrides::{OverrideSource, VecSource};
+#[cfg(feature = "async")]
+use super::remote::DynAsyncSource;
+use super::report::{BuildReport, KeyOverride, TypeChange};
+use super::resolve::{
+    lookup_value, resolve_references, Delimiters, ReferenceMode, ResolveOptions, DEFAULT_MAX_DEPTH,
+};
+use super::source::{
+    escape_segment, keys, path_exists, split_path, ConfigEntry, ConfigSource, Origin, Provenance,
+};
+use super::trace;
+#[cfg(feature = "watch")]
+use super::watch::{WatchedConfig, Watcher};
 use super::ConfigError;
 
-#[derive(Default)]
+const DEFAULT_PROFILE: &str = "default";
+const READER_PATH: &str = "<reader>";
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// File entries read ahead of the merge, keyed by path: by `parallel_load`, or
+// cached by a watched config
+pub(super) type Preloaded = HashMap<PathBuf, Result<Vec<ConfigEntry>, ConfigError>>;
+
+#[derive(Clone, Default)]
 #[must_use = "builders do nothing until .build() is called"]
 pub struct Config {
-    sources: Vec<Box<dyn ConfigSource>>,
+    sources: Vec<Source>,
+    // Registration index of each default; defaults are kept beneath
+    // everything else, as the leading sources, in this order
+    defaults: Vec<usize>,
+    // Relative file, dotenv and glob paths added afterwards are joined onto it
+    base_dir: Option<PathBuf>,
+    env: EnvOptions,
+    merge: MergeRules,
+    warn_cross_file: bool,
+    warn_type_changes: bool,
+    deny_type_changes: bool,
+    restrict_new_sections: bool,
+    optional_missing_is_error: bool,
+    debug_on_error: bool,
+    reference_mode: ReferenceMode,
+    delimiters: Delimiters,
+    resolve_keys: bool,
+    parallel_load: bool,
+    max_depth: Option<usize>,
+    required_keys: Vec<String>,
+    non_empty_keys: Vec<String>,
+    expected_sources: Option<usize>,
+    key_case: Option<KeyCase>,
+    // The process environment `${env:...}` references read from, once a
+    // watched config has frozen it; the live environment otherwise
+    reference_env: Option<HashMap<String, String>>,
+}
+
+// Env sources are kept distinct so builder-level env options apply to them at
+// build time, regardless of whether they were set before or after `with_env`
+#[derive(Debug, Clone)]
+enum Source {
+    Env(EnvSource),
+    Dotenv(DotenvSource),
+    // Expanded into one file source per match at build time
+    Glob(GlobSource),
+    // A pre-built table, merged as-is without parsing
+    Table(toml::Table),
+    Custom(Box<dyn ConfigSource>),
+    // Awaited by `build_async`, which swaps in its entries before merging
+    #[cfg(feature = "async")]
+    Async(Arc<dyn DynAsyncSource>),
+}
+
+impl Source {
+    fn is_required(&self) -> bool {
+        match self {
+            Source::Glob(glob) => glob.is_required(),
+            Source::Custom(source) => source.is_required(),
+            _ => false,
+        }
+    }
 }
 
 impl Config {
@@ -19,35 +105,1100 @@ impl Config {
         Self::default()
     }
 
+    pub fn from_table(table: toml::Table) -> Self {
+        let mut config = Self::default();
+        config.sources.push(Source::Table(table));
+        config
+    }
+
+    pub fn with_defaults<S: Serialize>(self, defaults: &S) -> Result<Self, ConfigError> {
+        let table = toml::Table::try_from(defaults)?;
+        Ok(self.push_default(Source::Table(table)))
+    }
+
+    pub fn with_default(self, path: impl Into<String>, value: impl Into<toml::Value>) -> Self {
+        let source = OverrideSource::new().set(path, value);
+        self.push_default(Source::Custom(Box::new(source)))
+    }
+
+    fn in_base_dir(&self, path: &Path) -> PathBuf {
+        match &self.base_dir {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    // Defaults always sit beneath every other source, in the order added
+    fn push_default(mut self, source: Source) -> Self {
+        self.defaults.push(self.sources.len());
+        self.sources.insert(self.defaults.len() - 1, source);
+        self
+    }
+
+    #[cfg(feature = "schemars")]
+    pub fn example<T: schemars::JsonSchema>() -> String {
+        example::render(schemars::schema_for!(T).as_value())
+    }
+
+    pub fn from_sources(sources: Vec<Box<dyn ConfigSource>>) -> Self {
+        let mut config = Self::default();
+        config.add_sources(sources);
+        config
+    }
+
+    pub fn add_sources(&mut self, sources: impl IntoIterator<Item = Box<dyn ConfigSource>>) {
+        self.sources.extend(sources.into_iter().map(Source::Custom));
+    }
+
+    pub fn with_base_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.base_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
     pub fn with_file(self, path: impl AsRef<Path>, required: bool) -> Self {
+        let path = self.in_base_dir(path.as_ref());
         self.with_source(FileSource::new(path, required))
     }
 
-    pub fn with_env(self, prefix: impl Into<String>, separator: impl Into<String>) -> Self {
-        self.with_source(EnvSource::new(prefix, separator))
+    pub fn with_reader(
+        mut self,
+        mut reader: impl Read,
+        format: Format,
+    ) -> Result<Self, ConfigError> {
+        let path = Path::new(READER_PATH);
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|source| ConfigError::ReadError {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        self.sources
+            .push(Source::Table(parse(bytes, format, path)?));
+        Ok(self)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn with_json_value(mut self, value: serde_json::Value) -> Result<Self, ConfigError> {
+        self.sources.push(Source::Table(json_to_table(value)?));
+        Ok(self)
+    }
+
+    pub fn with_glob(mut self, pattern: impl Into<String>, required: bool) -> Self {
+        let mut pattern = pattern.into();
+        if let (Some(base), true) = (&self.base_dir, Path::new(&pattern).is_relative()) {
+            // The base dir is a literal path, so its own `*` or `[` must not match
+            let base = ::glob::Pattern::escape(&base.to_string_lossy());
+            pattern = Path::new(&base)
+                .join(pattern)
+                .to_string_lossy()
+                .into_owned();
+        }
+        self.sources
+            .push(Source::Glob(GlobSource::new(pattern, required)));
+        self
+    }
+
+    pub fn with_profile(self, base_dir: impl AsRef<Path>, profile: impl AsRef<str>) -> Self {
+        let base_dir = base_dir.as_ref();
+        let profile = profile.as_ref();
+        self.with_file(base_dir.join("base.toml"), true)
+            .with_file(base_dir.join(format!("{profile}.toml")), false)
+            .with_file(base_dir.join(format!("{profile}.local.toml")), false)
+    }
+
+    pub fn profile_from_env(var: &str) -> String {
+        std::env::var(var)
+            .ok()
+            .filter(|profile| !profile.is_empty())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+    }
+
+    pub fn with_env(mut self, prefix: impl Into<String>, separator: impl Into<String>) -> Self {
+        self.sources
+            .push(Source::Env(EnvSource::new(prefix, separator)));
+        self
+    }
+
+    pub fn with_env_vars(
+        mut self,
+        prefix: impl Into<String>,
+        separator: impl Into<String>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let env = EnvSource::new(prefix, separator).with_vars(vars);
+        self.sources.push(Source::Env(env));
+        self
+    }
+
+    pub fn with_env_flat(mut self, prefix: impl Into<String>) -> Self {
+        self.sources.push(Source::Env(EnvSource::flat(prefix)));
+        self
+    }
+
+    pub fn with_dotenv(
+        mut self,
+        path: impl AsRef<Path>,
+        prefix: impl Into<String>,
+        separator: impl Into<String>,
+        required: bool,
+    ) -> Self {
+        let path = self.in_base_dir(path.as_ref());
+        self.sources.push(Source::Dotenv(DotenvSource::new(
+            path, prefix, separator, required,
+        )));
+        self
+    }
+
+    pub fn with_env_prefixes(self, prefixes: &[&str], separator: impl Into<String>) -> Self {
+        let separator = separator.into();
+        prefixes.iter().fold(self, |config, prefix| {
+            config.with_env(*prefix, separator.clone())
+        })
+    }
+
+    pub fn with_env_strict(mut self, strict: bool) -> Self {
+        self.env.strict = strict;
+        self
+    }
+
+    pub fn with_env_skip_empty(mut self, skip: bool) -> Self {
+        self.env.skip_empty = skip;
+        self
+    }
+
+    pub fn with_env_bool_aliases(mut self, enabled: bool) -> Self {
+        self.env.bool_aliases = enabled;
+        self
+    }
+
+    #[cfg(feature = "json")]
+    pub fn with_env_json(mut self, enabled: bool) -> Self {
+        self.env.json = enabled;
+        self
+    }
+
+    pub fn with_env_existing_only(mut self, existing_only: bool) -> Self {
+        self.env.existing_only = existing_only;
+        self
+    }
+
+    pub fn with_env_allowlist(mut self, paths: &[&str]) -> Self {
+        self.env
+            .allowlist
+            .get_or_insert_with(Vec::new)
+            .extend(paths.iter().map(|path| path.to_string()));
+        self
+    }
+
+    pub fn with_env_denylist(mut self, paths: &[&str]) -> Self {
+        self.env
+            .denylist
+            .extend(paths.iter().map(|path| path.to_string()));
+        self
+    }
+
+    pub fn with_env_coercion(
+        mut self,
+        coerce: impl Fn(&str) -> toml::Value + Send + Sync + 'static,
+    ) -> Self {
+        self.env.coercion = Some(Arc::new(coerce));
+        self
+    }
+
+    pub fn with_override(self, path: impl Into<String>, value: impl Into<toml::Value>) -> Self {
+        self.with_source(OverrideSource::new().set(path, value))
     }
 
     pub fn with_source(mut self, source: impl ConfigSource + 'static) -> Self {
-        self.sources.push(Box::new(source));
+        self.sources.push(Source::Custom(Box::new(source)));
+        self
+    }
+
+    pub fn with_entries(self, entries: Vec<ConfigEntry>) -> Self {
+        self.with_source(VecSource::new(entries))
+    }
+
+    // The trait isn't imported: its blanket impl would make `describe` on
+    // sync sources ambiguous throughout this file
+    #[cfg(feature = "async")]
+    pub fn with_async_source(
+        mut self,
+        source: impl super::remote::AsyncConfigSource + 'static,
+    ) -> Self {
+        self.sources.push(Source::Async(Arc::new(source)));
+        self
+    }
+
+    pub fn with_array_merge(mut self, strategy: ArrayMerge) -> Self {
+        self.merge.arrays = strategy;
+        self
+    }
+
+    pub fn with_path_array_merge(mut self, path: &str, strategy: ArrayMerge) -> Self {
+        let path = normalize_path(path, self.key_case);
+        self.merge.paths.insert(split_path(&path), strategy);
+        self
+    }
+
+    pub fn with_array_key(mut self, path: &str, key: &str) -> Self {
+        let (path, key) = (
+            normalize_path(path, self.key_case),
+            normalize_path(key, self.key_case),
+        );
+        self.merge.keys.insert(split_path(&path), key);
+        self
+    }
+
+    pub fn with_unset_sentinel(mut self, sentinel: &str) -> Self {
+        self.merge.unset = Some(sentinel.to_string());
+        self
+    }
+
+    pub fn with_key_normalization(mut self, case: KeyCase) -> Self {
+        self.key_case = Some(case);
+        self.env.key_case = Some(case);
+        // Paths named before this call are respelled, as later ones will be
+        let respell = |keys: Vec<String>| keys.iter().map(|key| case.apply(key)).collect();
+        self.required_keys = respell(std::mem::take(&mut self.required_keys));
+        self.non_empty_keys = respell(std::mem::take(&mut self.non_empty_keys));
+        self.merge.paths = std::mem::take(&mut self.merge.paths)
+            .into_iter()
+            .map(|(path, strategy)| (respell(path), strategy))
+            .collect();
+        self.merge.keys = std::mem::take(&mut self.merge.keys)
+            .into_iter()
+            .map(|(path, key)| (respell(path), case.apply(&key)))
+            .collect();
+        self
+    }
+
+    pub fn warn_duplicate_cross_file(mut self, warn: bool) -> Self {
+        self.warn_cross_file = warn;
+        self
+    }
+
+    pub fn warn_type_changes(mut self, warn: bool) -> Self {
+        self.warn_type_changes = warn;
+        self
+    }
+
+    pub fn deny_type_changes(mut self, deny: bool) -> Self {
+        self.deny_type_changes = deny;
+        self
+    }
+
+    pub fn restrict_new_sections(mut self, enabled: bool) -> Self {
+        self.restrict_new_sections = enabled;
+        self
+    }
+
+    pub fn treat_optional_missing_as_error(mut self, enabled: bool) -> Self {
+        self.optional_missing_is_error = enabled;
+        self
+    }
+
+    pub fn debug_on_error(mut self, enabled: bool) -> Self {
+        self.debug_on_error = enabled;
+        self
+    }
+
+    pub fn parallel_load(mut self, enabled: bool) -> Self {
+        self.parallel_load = enabled;
+        self
+    }
+
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn require_keys(mut self, paths: &[&str]) -> Self {
+        let case = self.key_case;
+        self.required_keys
+            .extend(paths.iter().map(|path| normalize_path(path, case)));
+        self
+    }
+
+    pub fn require_non_empty(mut self, paths: &[&str]) -> Self {
+        let case = self.key_case;
+        self.non_empty_keys
+            .extend(paths.iter().map(|path| normalize_path(path, case)));
+        self
+    }
+
+    pub fn expect_source_count(mut self, count: usize) -> Self {
+        self.expected_sources = Some(count);
+        self
+    }
+
+    pub fn reference_mode(mut self, mode: ReferenceMode) -> Self {
+        self.reference_mode = mode;
+        self
+    }
+
+    pub fn with_reference_delimiters(mut self, open: &str, close: &str) -> Self {
+        self.delimiters = Delimiters::new(open, close);
+        self
+    }
+
+    pub fn resolve_keys(mut self, enabled: bool) -> Self {
+        self.resolve_keys = enabled;
         self
     }
 
     pub fn build<T: DeserializeOwned>(self) -> Result<T, ConfigError> {
+        self.load()
+    }
+
+    // Async sources are awaited one at a time in registration order, then the
+    // build is the same as `build`
+    #[cfg(feature = "async")]
+    pub async fn build_async<T: DeserializeOwned>(mut self) -> Result<T, ConfigError> {
+        for source in &mut self.sources {
+            if let Source::Async(pending) = source {
+                *source = Source::Custom(Box::new(pending.fetch().await?));
+            }
+        }
+        self.load()
+    }
+
+    pub fn build_with<T, E>(self, map_err: impl FnOnce(ConfigError) -> E) -> Result<T, E>
+    where
+        T: DeserializeOwned,
+    {
+        self.build().map_err(map_err)
+    }
+
+    // Leaked on purpose: the config lives for the rest of the process
+    pub fn build_static<T: DeserializeOwned + 'static>(self) -> Result<&'static T, ConfigError> {
+        Ok(Box::leak(Box::new(self.build()?)))
+    }
+
+    pub fn build_collecting<T: DeserializeOwned>(self) -> Result<T, ConfigError> {
+        let merged = self.merge_sources(true, &mut BuildReport::default())?;
+        self.deserialize_merged(merged)
+    }
+
+    pub fn build_with_report<T: DeserializeOwned>(self) -> Result<(T, BuildReport), ConfigError> {
+        let mut report = BuildReport::default();
+        let merged = self.merge_sources(false, &mut report)?;
+        Ok((self.deserialize_merged(merged)?, report))
+    }
+
+    pub fn build_with_keys<T: DeserializeOwned>(self) -> Result<(T, Vec<String>), ConfigError> {
+        let merged = self.merged_table()?;
+        let keys = keys(&merged);
+        Ok((self.deserialize_merged(merged)?, keys))
+    }
+
+    // Collects load errors like `build_collecting` so CI sees every broken source
+    pub fn check(self) -> Result<(), ConfigError> {
+        self.merge_sources(true, &mut BuildReport::default())
+            .map(drop)
+    }
+
+    pub fn build_with_origins<T: DeserializeOwned>(
+        self,
+    ) -> Result<(T, HashMap<String, Origin>), ConfigError> {
+        let (merged, provenance) = self.merge_tracked(false, &mut BuildReport::default())?;
+        let origins = provenance.origins(&merged);
+        Ok((self.deserialize_merged(merged)?, origins))
+    }
+
+    pub fn build_with_fingerprint<T: DeserializeOwned>(self) -> Result<(T, String), ConfigError> {
+        let merged = self.merged_table()?;
+        let fingerprint = fingerprint(&toml::to_string(&merged)?);
+        Ok((self.deserialize_merged(merged)?, fingerprint))
+    }
+
+    pub fn build_table(self) -> Result<toml::Table, ConfigError> {
+        self.merged_table()
+    }
+
+    // `toml::Table` is a sorted map, so equal inputs always render identically
+    pub fn dump(self) -> Result<String, ConfigError> {
+        Ok(toml::to_string_pretty(&self.merged_table()?)?)
+    }
+
+    pub fn build_section<T: DeserializeOwned>(self, path: &str) -> Result<T, ConfigError> {
+        let merged = self.merged_table()?;
+        let section = lookup_value(&merged, &normalize_path(path, self.key_case))?.clone();
+        let dump = self.error_dump(&merged);
+        deserialize(section).map_err(|e| {
+            let e = match e {
+                ConfigError::DeserializeAt { path: key, source } => ConfigError::DeserializeAt {
+                    path: format!("{path}.{key}"),
+                    source,
+                },
+                ConfigError::MissingField(field) => {
+                    ConfigError::MissingField(format!("{path}.{field}"))
+                }
+                ConfigError::ExpectedTable { path: key } => ConfigError::ExpectedTable {
+                    path: format!("{path}.{key}"),
+                },
+                ConfigError::InvalidEnumValue {
+                    path: key,
+                    value,
+                    expected,
+                } => ConfigError::InvalidEnumValue {
+                    path: format!("{path}.{key}"),
+                    value,
+                    expected,
+                },
+                other => other,
+            };
+            attach_dump(e, dump)
+        })
+    }
+
+    #[cfg(feature = "watch")]
+    pub fn build_watched<T>(self) -> Result<(WatchedConfig<T>, Watcher), ConfigError>
+    where
+        T: DeserializeOwned + Send + Sync + 'static,
+    {
+        super::watch::watch(self.snapshot_env()?)
+    }
+
+    pub fn build_finalized<T: DeserializeOwned + Finalize>(self) -> Result<T, ConfigError> {
+        let mut config: T = self.build()?;
+        config.finalize().map_err(ConfigError::Finalize)?;
+        Ok(config)
+    }
+
+    pub fn build_validated<T, F>(self, validate: F) -> Result<T, ConfigError>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(&T) -> Result<(), String>,
+    {
+        let config = self.build()?;
+        validate(&config).map_err(ConfigError::Validation)?;
+        Ok(config)
+    }
+}
+
+impl Config {
+    pub(super) fn load<T: DeserializeOwned>(&self) -> Result<T, ConfigError> {
+        let merged = self.merged_table()?;
+
+        // Deserialize into the target type
+        self.deserialize_merged(merged)
+    }
+
+    fn deserialize_merged<T: DeserializeOwned>(
+        &self,
+        merged: toml::Table,
+    ) -> Result<T, ConfigError> {
+        let dump = self.error_dump(&merged);
+        deserialize(toml::Value::Table(merged)).map_err(|e| attach_dump(e, dump))
+    }
+
+    // Only rendered when opted in: the dump may contain secrets
+    fn error_dump(&self, merged: &toml::Table) -> Option<String> {
+        self.debug_on_error
+            .then(|| toml::to_string_pretty(merged).unwrap_or_else(|e| e.to_string()))
+    }
+
+    fn merged_table(&self) -> Result<toml::Table, ConfigError> {
+        self.merge_sources(false, &mut BuildReport::default())
+    }
+
+    fn merge_sources(
+        &self,
+        collect_errors: bool,
+        report: &mut BuildReport,
+    ) -> Result<toml::Table, ConfigError> {
+        self.merge_tracked(collect_errors, report)
+            .map(|(merged, _)| merged)
+    }
+
+    fn merge_tracked(
+        &self,
+        collect_errors: bool,
+        report: &mut BuildReport,
+    ) -> Result<(toml::Table, Provenance), ConfigError> {
+        let preloaded = match self.parallel_load {
+            true => self.read_files(&self.file_sources()),
+            false => Preloaded::new(),
+        };
+        self.merge_preloaded(collect_errors, report, preloaded)
+    }
+
+    fn merge_preloaded(
+        &self,
+        collect_errors: bool,
+        report: &mut BuildReport,
+        mut preloaded: Preloaded,
+    ) -> Result<(toml::Table, Provenance), ConfigError> {
+        let _span = trace::enter_build(self.sources.len());
+        if let Some(expected) = self.expected_sources {
+            let actual = self.sources.len();
+            if actual != expected {
+                return Err(ConfigError::SourceCountMismatch { expected, actual });
+            }
+        }
         let mut merged = toml::Table::new();
+        let mut provenance = Provenance::default();
+        let mut errors = Vec::new();
+        // Top-level keys of the defaults and every source up to the last
+        // required one (or just the first source, if none is), the base that
+        // later sources may only modify under `restrict_new_sections`
+        let mut sections: Option<HashSet<String>> = None;
+        let base_end = self
+            .sources
+            .iter()
+            .rposition(Source::is_required)
+            .unwrap_or(self.defaults.len());
 
-        for source in self.sources {
-            let entries = source.entries()?;
-            for entry in entries {
-                merge_at_path(&mut merged, &entry.path, entry.value);
+        for (index, source) in self.sources.iter().enumerate() {
+            let default = index < self.defaults.len();
+            let merge = self
+                .merge_source(
+                    source,
+                    default,
+                    &mut merged,
+                    &mut provenance,
+                    &mut preloaded,
+                    report,
+                )
+                .and_then(|()| match &mut sections {
+                    Some(known) => check_new_sections(known, &merged),
+                    None => Ok(()),
+                });
+            if self.restrict_new_sections && index == base_end {
+                sections = Some(merged.keys().cloned().collect());
+            }
+            match merge {
+                Ok(()) => {}
+                Err(e) if collect_errors => errors.push(e),
+                Err(e) => return Err(e),
             }
         }
 
+        match errors.len() {
+            0 => {}
+            1 => return Err(errors.remove(0)),
+            _ => return Err(ConfigError::Multiple(errors)),
+        }
+
         // Resolve ${...} references after all sources are merged
-        resolve_references(&mut merged)?;
+        let options = ResolveOptions {
+            mode: self.reference_mode,
+            max_depth: self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+            keys: self.resolve_keys,
+            delimiters: &self.delimiters,
+            key_case: self.key_case,
+            env: self.reference_env.as_ref(),
+        };
+        let dangling = resolve_references(&mut merged, &provenance, &options)?;
+        report.unresolved_references.extend(dangling);
 
-        // Deserialize into the target type
-        let value = toml::Value::Table(merged);
-        value.try_into().map_err(ConfigError::DeserializeError)
+        // TOML has no null, so a key that exists always holds a value
+        if let Some(missing) = self
+            .required_keys
+            .iter()
+            .find(|key| !path_exists(&merged, &split_path(key)))
+        {
+            return Err(ConfigError::MissingRequiredKey(missing.clone()));
+        }
+        // Numbers, booleans and datetimes are never blank; their type is left
+        // to deserialization
+        for key in &self.non_empty_keys {
+            let blank = match lookup_value(&merged, key) {
+                Ok(toml::Value::String(value)) => value.trim().is_empty(),
+                Ok(toml::Value::Array(items)) => items.is_empty(),
+                Ok(toml::Value::Table(table)) => table.is_empty(),
+                Ok(_) => false,
+                Err(_) => return Err(ConfigError::MissingRequiredKey(key.clone())),
+            };
+            if blank {
+                return Err(ConfigError::EmptyRequiredValue(key.clone()));
+            }
+        }
+
+        Ok((merged, provenance))
+    }
+
+    // Every file behind a file or glob source, in registration order
+    pub(super) fn file_sources(&self) -> Vec<Box<dyn ConfigSource>> {
+        self.sources
+            .iter()
+            .filter_map(files_behind)
+            .flatten()
+            .collect()
+    }
+
+    #[cfg(feature = "watch")]
+    pub(super) fn file_sources_at(&self, index: usize) -> Option<Vec<Box<dyn ConfigSource>>> {
+        self.sources
+            .get(self.position(index)?)
+            .and_then(files_behind)
+    }
+
+    // Where the source registered `index`-th sits in `sources`, which moves
+    // defaults ahead of everything registered before them
+    #[cfg(feature = "watch")]
+    fn position(&self, index: usize) -> Option<usize> {
+        if index >= self.sources.len() {
+            return None;
+        }
+        Some(match self.defaults.iter().position(|&at| at == index) {
+            Some(default) => default,
+            None => index + self.defaults.iter().filter(|&&at| at > index).count(),
+        })
+    }
+
+    // With `parallel_load` each file is read and parsed on its own thread;
+    // merging still happens in registration order, taking entries from the result
+    pub(super) fn read_files(&self, files: &[Box<dyn ConfigSource>]) -> Preloaded {
+        if !self.parallel_load {
+            return files
+                .iter()
+                .filter_map(|file| Some((file.path()?.to_path_buf(), file.entries())))
+                .collect();
+        }
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = files
+                .iter()
+                .map(|file| scope.spawn(|| file.entries()))
+                .collect();
+            files
+                .iter()
+                .zip(handles)
+                .filter_map(|(file, handle)| {
+                    let entries = handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                    Some((file.path()?.to_path_buf(), entries))
+                })
+                .collect()
+        })
+    }
+
+    // Merges with every file entry already in `preloaded`, so only files
+    // missing from it are read
+    #[cfg(feature = "watch")]
+    pub(super) fn load_preloaded<T: DeserializeOwned>(
+        &self,
+        preloaded: Preloaded,
+    ) -> Result<T, ConfigError> {
+        let report = &mut BuildReport::default();
+        let (merged, _) = self.merge_preloaded(false, report, preloaded)?;
+        self.deserialize_merged(merged)
+    }
+
+    fn merge_source(
+        &self,
+        source: &Source,
+        default: bool,
+        merged: &mut toml::Table,
+        provenance: &mut Provenance,
+        preloaded: &mut Preloaded,
+        report: &mut BuildReport,
+    ) -> Result<(), ConfigError> {
+        // Defaults keep their file path for reports, but not as their origin
+        let origin = |origin: Origin| if default { Origin::Default } else { origin };
+        match source {
+            Source::Env(env) => {
+                let entries = self.normalize_entries(env.entries_with(&self.env)?)?;
+                let entries = existing_env_entries(entries, &self.env, merged);
+                check_integer_overflows(&entries, &self.env, report)?;
+                trace::env_matched(&env.describe(), entries.len());
+                report.record_source(env.describe(), entries.len(), None);
+                let (origin, strict) = (origin(Origin::Env), self.env.strict);
+                self.merge_entries(entries, &origin, strict, merged, provenance, report)
+            }
+            Source::Dotenv(dotenv) => {
+                let entries = self.normalize_entries(dotenv.entries_with(&self.env)?)?;
+                let entries = existing_env_entries(entries, &self.env, merged);
+                self.check_optional_missing(&entries, dotenv.path())?;
+                check_integer_overflows(&entries, &self.env, report)?;
+                trace::env_matched(&dotenv.describe(), entries.len());
+                report.record_source(dotenv.describe(), entries.len(), dotenv.path());
+                let (origin, strict) = (origin(dotenv.origin()), self.env.strict);
+                self.merge_entries(entries, &origin, strict, merged, provenance, report)
+            }
+            Source::Glob(glob) => {
+                let mut count = 0;
+                for file in glob.files()? {
+                    let entries = take_entries(&file, preloaded)?;
+                    count += entries.len();
+                    let origin = origin(file.origin());
+                    self.merge_entries(entries, &origin, false, merged, provenance, report)?;
+                }
+                report.record_source(glob.describe(), count, None);
+                Ok(())
+            }
+            Source::Table(table) => {
+                let entries = vec![ConfigEntry::root(table.clone())];
+                report.record_source("table".to_string(), entries.len(), None);
+                let origin = origin(Origin::Override);
+                self.merge_entries(entries, &origin, false, merged, provenance, report)
+            }
+            Source::Custom(source) => {
+                let mut entries = take_entries(source.as_ref(), preloaded)?;
+                self.check_optional_missing(&entries, source.path())?;
+                // A custom env source brings its own merge-time checks
+                let strict = match source.env_options() {
+                    Some(options) => {
+                        entries = self.normalize_entries(entries)?;
+                        entries = existing_env_entries(entries, options, merged);
+                        check_integer_overflows(&entries, options, report)?;
+                        options.strict
+                    }
+                    None => false,
+                };
+                report.record_source(source.describe(), entries.len(), source.path());
+                let origin = origin(source.origin());
+                self.merge_entries(entries, &origin, strict, merged, provenance, report)
+            }
+            #[cfg(feature = "async")]
+            Source::Async(source) => Err(ConfigError::AsyncSource(format!("{source:?}"))),
+        }
+    }
+
+    // A missing required file has already failed with `FileNotFound`, so a
+    // file-backed source that comes back empty with its file gone was optional
+    fn check_optional_missing(
+        &self,
+        entries: &[ConfigEntry],
+        path: Option<&Path>,
+    ) -> Result<(), ConfigError> {
+        match path {
+            Some(path)
+                if self.optional_missing_is_error && entries.is_empty() && !path.exists() =>
+            {
+                Err(ConfigError::OptionalFileMissing(path.to_path_buf()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Env entries are normalized ahead of the merge too, so `existing_only`
+    // compares them against keys spelled the same way
+    fn normalize_entries(
+        &self,
+        entries: Vec<ConfigEntry>,
+    ) -> Result<Vec<ConfigEntry>, ConfigError> {
+        match self.key_case {
+            Some(case) => entries
+                .into_iter()
+                .map(|entry| normalize_entry(entry, case))
+                .collect(),
+            None => Ok(entries),
+        }
+    }
+
+    fn merge_entries(
+        &self,
+        entries: Vec<ConfigEntry>,
+        origin: &Origin,
+        strict: bool,
+        merged: &mut toml::Table,
+        provenance: &mut Provenance,
+        report: &mut BuildReport,
+    ) -> Result<(), ConfigError> {
+        for entry in self.normalize_entries(entries)? {
+            if strict {
+                check_type_conflict(merged, &entry.path, &entry.value)?;
+            }
+            if self.warn_type_changes || self.deny_type_changes {
+                for (path, from, to) in type_changes(merged, &entry.path, &entry.value, &self.merge)
+                {
+                    if self.deny_type_changes {
+                        return Err(ConfigError::TypeChange { path, from, to });
+                    }
+                    report.type_changes.push(TypeChange { path, from, to });
+                }
+            }
+            if let (true, Some(current)) = (self.warn_cross_file, origin.path()) {
+                for (key, previous) in provenance.overridden_by(&entry.path, &entry.value) {
+                    if previous != current {
+                        report.overrides.push(KeyOverride {
+                            path: key.join("."),
+                            previous: previous.to_path_buf(),
+                            current: current.to_path_buf(),
+                        });
+                    }
+                }
+            }
+            provenance.record(&entry.path, &entry.value, origin);
+            merge_at_path(merged, &entry.path, entry.value, &self.merge)?;
+        }
+
+        Ok(())
+    }
+
+    // Env and dotenv variables, and the environment `${env:...}` references
+    // read, are captured once, at startup; a watched config only ever reloads
+    // files
+    #[cfg(feature = "watch")]
+    fn snapshot_env(mut self) -> Result<Self, ConfigError> {
+        for source in &mut self.sources {
+            match source {
+                Source::Env(env) => env.snapshot(),
+                Source::Dotenv(dotenv) => dotenv.snapshot()?,
+                _ => {}
+            }
+        }
+        // Non-Unicode variables are left out, as `std::env::var` rejects them
+        let vars = std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        });
+        self.reference_env.get_or_insert_with(|| vars.collect());
+        Ok(self)
+    }
+
+    // Dotenv files are left out: their variables are snapshotted at startup
+    #[cfg(feature = "watch")]
+    pub(super) fn file_paths(&self) -> Vec<PathBuf> {
+        self.sources
+            .iter()
+            .flat_map(|source| match source {
+                Source::Env(_) | Source::Dotenv(_) | Source::Table(_) => Vec::new(),
+                Source::Glob(glob) => glob.paths().unwrap_or_default(),
+                Source::Custom(source) => {
+                    source.path().map(Path::to_path_buf).into_iter().collect()
+                }
+                #[cfg(feature = "async")]
+                Source::Async(_) => Vec::new(),
+            })
+            .collect()
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(value: toml::Value) -> Result<T, ConfigError> {
+    trace::deserializing::<T>();
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = dotted_path(e.path());
+        let source = e.into_inner();
+        // The path points at the struct missing the field, so append its name
+        if let Some(field) = missing_field(&source) {
+            let field = escape_segment(field);
+            return ConfigError::MissingField(match path.as_str() {
+                "" => field,
+                _ => format!("{path}.{field}"),
+            });
+        }
+        // A root-level failure has no key to point at
+        if path.is_empty() {
+            return ConfigError::DeserializeError(source);
+        }
+        if expects_table(&source) {
+            return ConfigError::ExpectedTable { path };
+        }
+        if let Some((value, expected)) = unknown_variant(&source) {
+            return ConfigError::InvalidEnumValue {
+                path,
+                value,
+                expected,
+            };
+        }
+        ConfigError::DeserializeAt {
+            path,
+            source: Box::new(source),
+        }
+    })
+}
+
+// Spelled like every other path in the crate (`servers.0.port`, with `\.`
+// for a dot inside a key) rather than serde's `servers[0].port`, so a `key()`
+// can be passed back to `require_keys` or `build_section`
+fn dotted_path(path: &serde_path_to_error::Path) -> String {
+    let segments: Vec<String> = path
+        .iter()
+        .map(|segment| match segment {
+            Segment::Seq { index } => index.to_string(),
+            Segment::Map { key } => escape_segment(key),
+            Segment::Enum { variant } => escape_segment(variant),
+            Segment::Unknown => "?".to_string(),
+        })
+        .collect();
+    segments.join(".")
+}
+
+// 64-bit FNV-1a over the rendered table, which like `dump` is sorted and so
+// the same on every run; not meant to resist deliberate collisions
+fn fingerprint(rendered: &str) -> String {
+    let hash = rendered.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{hash:016x}")
+}
+
+fn missing_field(error: &toml::de::Error) -> Option<&str> {
+    error
+        .message()
+        .strip_prefix("missing field `")?
+        .split_once('`')
+        .map(|(field, _)| field)
+}
+
+// Serde's "invalid type: string \"x\", expected struct Db" (or "a map"): a
+// scalar, typically from env, where a table belongs
+fn expects_table(error: &toml::de::Error) -> bool {
+    let Some((found, expected)) = error
+        .message()
+        .strip_prefix("invalid type: ")
+        .and_then(|rest| rest.rsplit_once(", expected "))
+    else {
+        return false;
+    };
+    let scalar = ["string", "integer", "floating point", "boolean"]
+        .iter()
+        .any(|kind| found.starts_with(kind));
+    scalar && (expected == "a map" || expected.starts_with("struct "))
+}
+
+// Parses serde's "unknown variant `x`, expected one of `a`, `b`" (also the
+// "expected `a` or `b`" and "expected `a`" forms for fewer variants)
+fn unknown_variant(error: &toml::de::Error) -> Option<(String, Vec<String>)> {
+    let (value, expected) = error
+        .message()
+        .strip_prefix("unknown variant `")?
+        .split_once("`, expected ")?;
+    let expected = expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(String::from)
+        .collect();
+    Some((value.to_string(), expected))
+}
+
+// New keys are added to `known` once reported, so a collecting build names
+// each section only once
+fn check_new_sections(
+    known: &mut HashSet<String>,
+    merged: &toml::Table,
+) -> Result<(), ConfigError> {
+    let mut added: Vec<&String> = merged.keys().filter(|key| !known.contains(*key)).collect();
+    added.sort();
+    let Some(first) = added.first().map(|key| key.to_string()) else {
+        return Ok(());
+    };
+    known.extend(added.into_iter().cloned());
+    Err(ConfigError::UnknownSection(first))
+}
+
+// The files behind a file or glob source; `None` for every other kind
+fn files_behind(source: &Source) -> Option<Vec<Box<dyn ConfigSource>>> {
+    match source {
+        Source::Custom(source) if source.path().is_some() => Some(vec![source.clone()]),
+        // A failing glob is left for the merge to report
+        Source::Glob(glob) => Some(
+            glob.files()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|file| Box::new(file) as Box<dyn ConfigSource>)
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn take_entries(
+    source: &dyn ConfigSource,
+    preloaded: &mut Preloaded,
+) -> Result<Vec<ConfigEntry>, ConfigError> {
+    // A path registered twice was only preloaded once; later uses read again
+    match source.path().and_then(|path| preloaded.remove(path)) {
+        Some(entries) => entries,
+        None => source.entries(),
+    }
+}
+
+// Env digits beyond i64 stay strings; strict mode rejects them instead
+fn check_integer_overflows(
+    entries: &[ConfigEntry],
+    options: &EnvOptions,
+    report: &mut BuildReport,
+) -> Result<(), ConfigError> {
+    for entry in entries {
+        let mut path = entry.path.clone();
+        check_value_overflows(&entry.value, &mut path, options, report)?;
+    }
+    Ok(())
+}
+
+// A JSON env value is walked too: its out-of-range integers are kept as
+// text just like a plain env value's
+fn check_value_overflows(
+    value: &toml::Value,
+    path: &mut Vec<String>,
+    options: &EnvOptions,
+    report: &mut BuildReport,
+) -> Result<(), ConfigError> {
+    match value {
+        toml::Value::String(text) if overflows_integer(text) => {
+            let key = path.join(".");
+            if options.strict {
+                let value = text.clone();
+                return Err(ConfigError::IntegerOverflow { path: key, value });
+            }
+            report.integer_overflows.push(key);
+        }
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                path.push(key.clone());
+                check_value_overflows(value, path, options, report)?;
+                path.pop();
+            }
+        }
+        toml::Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push(index.to_string());
+                check_value_overflows(item, path, options, report)?;
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// With `existing_only`, env values may only override keys set earlier;
+// keys of a JSON table that aren't set yet are dropped one by one, and an
+// entry left with an empty table is dropped whole
+fn existing_env_entries(
+    mut entries: Vec<ConfigEntry>,
+    options: &EnvOptions,
+    merged: &toml::Table,
+) -> Vec<ConfigEntry> {
+    if options.existing_only {
+        entries.retain_mut(|entry| {
+            if !path_exists(merged, &entry.path) {
+                return false;
+            }
+            retain_existing(merged, &mut entry.path, &mut entry.value);
+            !matches!(&entry.value, toml::Value::Table(table) if table.is_empty())
+        });
+    }
+    entries
+}
+
+fn retain_existing(merged: &toml::Table, path: &mut Vec<String>, value: &mut toml::Value) {
+    if let toml::Value::Table(table) = value {
+        table.retain(|key, value| {
+            path.push(key.to_string());
+            let exists = path_exists(merged, path);
+            if exists {
+                retain_existing(merged, path, value);
+            }
+            path.pop();
+            exists
+        });
+    }
+}
+
+fn attach_dump(error: ConfigError, dump: Option<String>) -> ConfigError {
+    match dump {
+        Some(table_dump) => ConfigError::DeserializeWithDump {
+            source: Box::new(error),
+            table_dump,
+        },
+        None => error,
     }
 }
 
@@ -55,6 +1206,32 @@ impl std::fmt::Debug for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Config")
             .field("sources", &self.sources)
+            .field("defaults", &self.defaults)
+            .field("base_dir", &self.base_dir)
+            .field("env", &self.env)
+            .field("merge", &self.merge)
+            .field("warn_cross_file", &self.warn_cross_file)
+            .field("warn_type_changes", &self.warn_type_changes)
+            .field("deny_type_changes", &self.deny_type_changes)
+            .field("restrict_new_sections", &self.restrict_new_sections)
+            .field("optional_missing_is_error", &self.optional_missing_is_error)
+            .field("debug_on_error", &self.debug_on_error)
+            .field("reference_mode", &self.reference_mode)
+            .field("delimiters", &self.delimiters)
+            .field("resolve_keys", &self.resolve_keys)
+            .field("parallel_load", &self.parallel_load)
+            .field("max_depth", &self.max_depth)
+            .field("required_keys", &self.required_keys)
+            .field("non_empty_keys", &self.non_empty_keys)
+            .field("expected_sources", &self.expected_sources)
+            .field("key_case", &self.key_case)
+            .field(
+                "reference_env",
+                &self
+                    .reference_env
+                    .as_ref()
+                    .map(|vars| format!("<{} vars>", vars.len())),
+            )
             .finish()
     }
 }
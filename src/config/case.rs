@@ -0,0 +1,77 @@
+use toml::{Table, Value};
+
+use super::source::ConfigEntry;
+use super::ConfigError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCase {
+    // `max-connections` becomes `max_connections`
+    SnakeCase,
+    // `max_connections` becomes `max-connections`
+    KebabCase,
+}
+
+impl KeyCase {
+    // Also used on whole dotted reference paths, as `.` is never rewritten
+    pub(super) fn apply(self, key: &str) -> String {
+        match self {
+            KeyCase::SnakeCase => key.replace('-', "_"),
+            KeyCase::KebabCase => key.replace('_', "-"),
+        }
+    }
+}
+
+// For dotted paths the user names keys with, so they match the merged keys
+pub(super) fn normalize_path(path: &str, case: Option<KeyCase>) -> String {
+    match case {
+        Some(case) => case.apply(path),
+        None => path.to_string(),
+    }
+}
+
+// Applied to each entry before it is merged, so a later layer using the other
+// spelling overrides the key instead of sitting beside it
+pub fn normalize_entry(entry: ConfigEntry, case: KeyCase) -> Result<ConfigEntry, ConfigError> {
+    let mut path: Vec<String> = entry.path.iter().map(|key| case.apply(key)).collect();
+    let value = normalize_value(entry.value, case, &mut path)?;
+    Ok(ConfigEntry::at_path(path, value))
+}
+
+fn normalize_value(
+    value: Value,
+    case: KeyCase,
+    path: &mut Vec<String>,
+) -> Result<Value, ConfigError> {
+    Ok(match value {
+        Value::Table(table) => Value::Table(normalize_table(table, case, path)?),
+        Value::Array(items) => {
+            let mut normalized = Vec::with_capacity(items.len());
+            for (index, item) in items.into_iter().enumerate() {
+                path.push(index.to_string());
+                normalized.push(normalize_value(item, case, path)?);
+                path.pop();
+            }
+            Value::Array(normalized)
+        }
+        other => other,
+    })
+}
+
+fn normalize_table(
+    table: Table,
+    case: KeyCase,
+    path: &mut Vec<String>,
+) -> Result<Table, ConfigError> {
+    let mut normalized = Table::new();
+    for (key, value) in table {
+        path.push(case.apply(&key));
+        let value = normalize_value(value, case, path)?;
+        let key = path.pop().expect("key was pushed");
+        if normalized.contains_key(&key) {
+            path.push(key);
+            return Err(ConfigError::KeyCollision(path.join(".")));
+        }
+        normalized.insert(key, value);
+    }
+    Ok(normalized)
+}
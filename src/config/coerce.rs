@@ -0,0 +1,48 @@
+use toml::Value;
+
+pub fn coerce_value(s: &str) -> Value {
+    // Try boolean first (case-insensitive)
+    if s.eq_ignore_ascii_case("true") {
+        return Value::Boolean(true);
+    }
+    if s.eq_ignore_ascii_case("false") {
+        return Value::Boolean(false);
+    }
+
+    // Try integer (only if it looks like an integer: optional minus, then digits)
+    if looks_like_integer(s) {
+        if let Ok(i) = s.parse::<i64>() {
+            return Value::Integer(i);
+        }
+    }
+
+    // Try float (if contains decimal point)
+    if s.contains('.') {
+        if let Ok(f) = s.parse::<f64>() {
+            return Value::Float(f);
+        }
+    }
+
+    // Fallback to string
+    Value::String(s.to_string())
+}
+
+pub fn coerce_bool_alias(s: &str) -> Option<bool> {
+    // `1`/`0` are deliberately excluded so numeric values stay integers
+    match s.to_ascii_lowercase().as_str() {
+        "yes" | "on" => Some(true),
+        "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+// Digits that `coerce_value` leaves as a string because they don't fit i64,
+// TOML's only integer type
+pub(super) fn overflows_integer(s: &str) -> bool {
+    looks_like_integer(s) && s.parse::<i64>().is_err()
+}
+
+fn looks_like_integer(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
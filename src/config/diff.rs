@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use toml::{Table, Value};
+
+use super::source::escape_segment;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiff {
+    pub path: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(Value),
+    Removed(Value),
+    Modified { from: Value, to: Value },
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.change {
+            Change::Added(value) => write!(f, "+ {} = {value}", self.path),
+            Change::Removed(value) => write!(f, "- {} = {value}", self.path),
+            Change::Modified { from, to } => write!(f, "~ {} = {from} -> {to}", self.path),
+        }
+    }
+}
+
+// Tables are walked in sorted key order and arrays by index, so equal inputs
+// always give the same list. A key or element present on one side only is
+// reported once, whole, rather than leaf by leaf
+pub fn diff(old: &Table, new: &Table) -> Vec<ConfigDiff> {
+    let mut changes = Vec::new();
+    diff_tables(old, new, None, &mut changes);
+    changes
+}
+
+fn diff_tables(old: &Table, new: &Table, path: Option<&str>, changes: &mut Vec<ConfigDiff>) {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for key in keys {
+        let segment = escape_segment(key);
+        let child = match path {
+            Some(path) => format!("{path}.{segment}"),
+            None => segment,
+        };
+        diff_values(old.get(key), new.get(key), child, changes);
+    }
+}
+
+fn diff_values(
+    old: Option<&Value>,
+    new: Option<&Value>,
+    path: String,
+    changes: &mut Vec<ConfigDiff>,
+) {
+    let change = match (old, new) {
+        (Some(Value::Table(old)), Some(Value::Table(new))) => {
+            return diff_tables(old, new, Some(&path), changes);
+        }
+        (Some(Value::Array(old)), Some(Value::Array(new))) => {
+            for index in 0..old.len().max(new.len()) {
+                diff_values(
+                    old.get(index),
+                    new.get(index),
+                    format!("{path}.{index}"),
+                    changes,
+                );
+            }
+            return;
+        }
+        (Some(old), Some(new)) if old == new => return,
+        (Some(from), Some(to)) => Change::Modified {
+            from: from.clone(),
+            to: to.clone(),
+        },
+        (Some(old), None) => Change::Removed(old.clone()),
+        (None, Some(new)) => Change::Added(new.clone()),
+        (None, None) => return,
+    };
+    changes.push(ConfigDiff { path, change });
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use super::env::{EnvOptions, EnvSource};
+use super::file::decode;
+use super::source::{ConfigEntry, ConfigSource};
+use super::ConfigError;
+
+#[derive(Clone)]
+pub struct DotenvSource {
+    path: PathBuf,
+    env: EnvSource,
+    required: bool,
+    // Read instead of the file once snapshotted
+    vars: Option<Vec<(String, String)>>,
+}
+
+// Manual impl: snapshotted variables may hold secrets, so only their count is shown
+impl std::fmt::Debug for DotenvSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DotenvSource")
+            .field("path", &self.path)
+            .field("env", &self.env)
+            .field("required", &self.required)
+            .field(
+                "vars",
+                &self
+                    .vars
+                    .as_ref()
+                    .map(|vars| format!("<{} vars>", vars.len())),
+            )
+            .finish()
+    }
+}
+
+impl DotenvSource {
+    pub fn new(
+        path: impl AsRef<Path>,
+        prefix: impl Into<String>,
+        separator: impl Into<String>,
+        required: bool,
+    ) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            env: EnvSource::new(prefix, separator),
+            required,
+            vars: None,
+        }
+    }
+
+    pub fn entries_with(&self, options: &EnvOptions) -> Result<Vec<ConfigEntry>, ConfigError> {
+        let vars = match &self.vars {
+            Some(vars) => vars.clone(),
+            None => self.read_vars()?,
+        };
+        Ok(self.env.entries_from(vars, options))
+    }
+
+    // Reads the file once, so later builds from this source never see it
+    // change; a missing optional file snapshots as empty
+    #[cfg(feature = "watch")]
+    pub(super) fn snapshot(&mut self) -> Result<(), ConfigError> {
+        if self.vars.is_none() {
+            self.vars = Some(self.read_vars()?);
+        }
+        Ok(())
+    }
+
+    fn read_vars(&self) -> Result<Vec<(String, String)>, ConfigError> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(bytes) => decode(bytes).ok_or_else(|| ConfigError::Encoding(self.path.clone()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if self.required {
+                    return Err(ConfigError::FileNotFound(self.path.clone()));
+                }
+                return Ok(vec![]);
+            }
+            Err(e) => {
+                return Err(ConfigError::ReadError {
+                    path: self.path.clone(),
+                    source: e,
+                })
+            }
+        };
+
+        parse_dotenv(&contents).map_err(|line| ConfigError::InvalidDotenv {
+            path: self.path.clone(),
+            line,
+        })
+    }
+}
+
+impl ConfigSource for DotenvSource {
+    fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+        self.entries_with(&EnvOptions::default())
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn describe(&self) -> String {
+        let required = if self.required {
+            "required"
+        } else {
+            "optional"
+        };
+        format!("dotenv:{} ({required})", self.path.display())
+    }
+}
+
+// Returns the 1-based number of the first malformed line on failure
+fn parse_dotenv(contents: &str) -> Result<Vec<(String, String)>, usize> {
+    let mut vars = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').ok_or(index + 1)?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(index + 1);
+        }
+
+        let value = parse_value(value.trim()).ok_or(index + 1)?;
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+fn parse_value(raw: &str) -> Option<String> {
+    match raw.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            // The first unescaped matching quote closes the value, so a
+            // comment after it may quote freely
+            let mut escaped = false;
+            let (end, _) = raw.char_indices().skip(1).find(|&(_, c)| {
+                let closes = c == quote && !escaped;
+                escaped = quote == '"' && c == '\\' && !escaped;
+                closes
+            })?;
+            let inner = &raw[1..end];
+            // Only a trailing comment may follow the closing quote
+            let rest = raw[end + 1..].trim_start();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return None;
+            }
+            Some(if quote == '"' {
+                unescape(inner)
+            } else {
+                inner.to_string()
+            })
+        }
+        // Unquoted values end at an inline ` #` comment
+        _ => Some(match raw.find(" #") {
+            Some(comment) => raw[..comment].trim_end().to_string(),
+            None => raw.to_string(),
+        }),
+    }
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(escaped @ ('"' | '\\')) => result.push(escaped),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
@@ -1,12 +1,122 @@
+use std::sync::Arc;
+
 use toml::Value;
 
-use super::source::{ConfigEntry, ConfigSource};
+use super::case::{normalize_path, KeyCase};
+#[cfg(feature = "json")]
+use super::coerce::overflows_integer;
+use super::coerce::{coerce_bool_alias, coerce_value};
+#[cfg(feature = "json")]
+use super::json::json_to_toml;
+use super::source::{split_path, ConfigEntry, ConfigSource, Origin};
 use super::ConfigError;
 
-#[derive(Debug, Clone)]
+pub type Coercion = Arc<dyn Fn(&str) -> Value + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct EnvOptions {
+    pub strict: bool,
+    pub skip_empty: bool,
+    pub bool_aliases: bool,
+    #[cfg(feature = "json")]
+    pub json: bool,
+    pub existing_only: bool,
+    // Dotted paths; each also covers everything beneath it
+    pub allowlist: Option<Vec<String>>,
+    pub denylist: Vec<String>,
+    pub coercion: Option<Coercion>,
+    // Both sides of an allow or deny match are normalized with it
+    pub key_case: Option<KeyCase>,
+}
+
+impl EnvOptions {
+    // The denylist wins over the allowlist
+    pub fn allows(&self, path: &[String]) -> bool {
+        let path: Vec<String> = path
+            .iter()
+            .map(|key| normalize_path(key, self.key_case))
+            .collect();
+        let covers =
+            |listed: &String| path.starts_with(&split_path(&normalize_path(listed, self.key_case)));
+        !self.denylist.iter().any(covers)
+            && self
+                .allowlist
+                .as_ref()
+                .is_none_or(|allowed| allowed.iter().any(covers))
+    }
+
+    // A structured value could otherwise set a denied key beneath its own path
+    fn strip_denied(&self, path: &mut Vec<String>, value: &mut Value) {
+        match value {
+            Value::Table(table) => table.retain(|key, value| {
+                path.push(key.to_string());
+                let allowed = self.allows(path);
+                if allowed {
+                    self.strip_denied(path, value);
+                }
+                path.pop();
+                allowed
+            }),
+            Value::Array(items) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    path.push(index.to_string());
+                    self.strip_denied(path, item);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl std::fmt::Debug for EnvOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("EnvOptions");
+        debug
+            .field("strict", &self.strict)
+            .field("skip_empty", &self.skip_empty)
+            .field("bool_aliases", &self.bool_aliases);
+        #[cfg(feature = "json")]
+        debug.field("json", &self.json);
+        debug
+            .field("existing_only", &self.existing_only)
+            .field("allowlist", &self.allowlist)
+            .field("denylist", &self.denylist)
+            .field("coercion", &self.coercion.as_ref().map(|_| "<fn>"))
+            .field("key_case", &self.key_case)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
 pub struct EnvSource {
     prefix: String,
     separator: String,
+    // Keep everything after the prefix as a single key instead of splitting
+    flat: bool,
+    // Read instead of the process environment when set
+    vars: Option<Vec<(String, String)>>,
+    // Used by `entries`; builder-managed env sources get the builder's options
+    options: EnvOptions,
+}
+
+// Manual impl: supplied variables may hold secrets, so only their count is shown
+impl std::fmt::Debug for EnvSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnvSource")
+            .field("prefix", &self.prefix)
+            .field("separator", &self.separator)
+            .field("flat", &self.flat)
+            .field(
+                "vars",
+                &self
+                    .vars
+                    .as_ref()
+                    .map(|vars| format!("<{} vars>", vars.len())),
+            )
+            .field("options", &self.options)
+            .finish()
+    }
 }
 
 impl EnvSource {
@@ -16,63 +126,154 @@ impl EnvSource {
         Self {
             prefix: prefix.into(),
             separator,
+            flat: false,
+            vars: None,
+            options: EnvOptions::default(),
         }
     }
-}
 
-impl ConfigSource for EnvSource {
-    fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+    pub fn with_vars(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.vars = Some(vars.into_iter().collect());
+        self
+    }
+
+    // Freezes the process environment as it is now, so later builds from
+    // this source never see variables changed afterwards
+    #[cfg(feature = "watch")]
+    pub(super) fn snapshot(&mut self) {
+        self.vars.get_or_insert_with(process_vars);
+    }
+
+    pub fn with_options(mut self, options: EnvOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn flat(prefix: impl Into<String>) -> Self {
+        Self {
+            flat: true,
+            ..Self::new(prefix, "_")
+        }
+    }
+
+    pub fn entries_with(&self, options: &EnvOptions) -> Result<Vec<ConfigEntry>, ConfigError> {
+        Ok(match &self.vars {
+            Some(vars) => self.entries_from(vars.iter().cloned(), options),
+            None => self.entries_from(process_vars(), options),
+        })
+    }
+
+    pub fn entries_from(
+        &self,
+        vars: impl IntoIterator<Item = (String, String)>,
+        options: &EnvOptions,
+    ) -> Vec<ConfigEntry> {
+        let coerce = options.coercion.as_deref().unwrap_or(&coerce_value);
         let prefix_with_sep = format!("{}{}", self.prefix, self.separator);
         let mut entries = Vec::new();
 
-        for (key, value) in std::env::vars() {
+        for (key, value) in vars {
             if let Some(path_str) = key.strip_prefix(&prefix_with_sep) {
-                if path_str.is_empty() {
+                if path_str.is_empty() || (options.skip_empty && value.is_empty()) {
                     continue;
                 }
 
-                let path: Vec<String> = path_str
-                    .split(&self.separator)
-                    .map(|s| s.to_lowercase())
-                    .collect();
+                let mut path: Vec<String> = if self.flat {
+                    vec![path_str.to_lowercase()]
+                } else {
+                    path_str
+                        .split(&self.separator)
+                        .map(|s| s.to_lowercase())
+                        .collect()
+                };
+                if !options.allows(&path) {
+                    continue;
+                }
 
-                let coerced_value = coerce_value(&value);
+                let mut coerced_value = match coerce_bool_alias(&value) {
+                    Some(flag) if options.bool_aliases => Value::Boolean(flag),
+                    _ => structured(&value, options).unwrap_or_else(|| coerce(&value)),
+                };
+                options.strip_denied(&mut path, &mut coerced_value);
                 entries.push(ConfigEntry::at_path(path, coerced_value));
             }
         }
 
-        Ok(entries)
+        entries
     }
 }
 
-fn coerce_value(s: &str) -> Value {
-    // Try boolean first (case-insensitive)
-    if s.eq_ignore_ascii_case("true") {
-        return Value::Boolean(true);
-    }
-    if s.eq_ignore_ascii_case("false") {
-        return Value::Boolean(false);
-    }
+// Sorted so variables that map to the same key (`APP__A` and `APP__a`) merge
+// in a fixed order, whatever order the OS uses
+fn process_vars() -> Vec<(String, String)> {
+    let mut vars: Vec<_> = std::env::vars().collect();
+    vars.sort();
+    vars
+}
 
-    // Try integer (only if it looks like an integer: optional minus, then digits)
-    if looks_like_integer(s) {
-        if let Ok(i) = s.parse::<i64>() {
-            return Value::Integer(i);
-        }
+// With `json`, a value holding a JSON object or array becomes the matching
+// TOML table or array; anything that doesn't parse is coerced as usual
+#[cfg(feature = "json")]
+fn structured(value: &str, options: &EnvOptions) -> Option<Value> {
+    if !options.json || !value.trim_start().starts_with(['{', '[']) {
+        return None;
     }
+    json_to_toml(serde_json::from_str(&quote_wide_integers(value)).ok()?)
+}
 
-    // Try float (if contains decimal point)
-    if s.contains('.') {
-        if let Ok(f) = s.parse::<f64>() {
-            return Value::Float(f);
+// serde_json reads an integer literal beyond u64 as a float (`1e20`); quoted
+// first, any literal out of i64 range is kept as its text, like a plain env
+// value, and reported as an overflow
+#[cfg(feature = "json")]
+fn quote_wide_integers(json: &str) -> String {
+    let mut quoted = String::with_capacity(json.len());
+    let (mut in_string, mut escaped) = (false, false);
+    let mut rest = json;
+
+    while let Some(ch) = rest.chars().next() {
+        if !in_string && (ch == '-' || ch.is_ascii_digit()) {
+            let end = rest
+                .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                .unwrap_or(rest.len());
+            let number = &rest[..end];
+            match overflows_integer(number) {
+                true => quoted.push_str(&format!("\"{number}\"")),
+                false => quoted.push_str(number),
+            }
+            rest = &rest[end..];
+            continue;
         }
+        match (in_string, escaped, ch) {
+            (true, true, _) => escaped = false,
+            (true, false, '\\') => escaped = true,
+            (_, false, '"') => in_string = !in_string,
+            _ => {}
+        }
+        quoted.push(ch);
+        rest = &rest[ch.len_utf8()..];
     }
+    quoted
+}
 
-    // Fallback to string
-    Value::String(s.to_string())
+#[cfg(not(feature = "json"))]
+fn structured(_value: &str, _options: &EnvOptions) -> Option<Value> {
+    None
 }
 
-fn looks_like_integer(s: &str) -> bool {
-    let s = s.strip_prefix('-').unwrap_or(s);
-    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+impl ConfigSource for EnvSource {
+    fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+        self.entries_with(&self.options)
+    }
+
+    fn describe(&self) -> String {
+        format!("env:{}{}*", self.prefix, self.separator)
+    }
+
+    fn env_options(&self) -> Option<&EnvOptions> {
+        Some(&self.options)
+    }
+
+    fn origin(&self) -> Origin {
+        Origin::Env
+    }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -7,6 +7,9 @@ pub enum ConfigError {
     #[error("required config file not found: {0}")]
     FileNotFound(PathBuf),
 
+    #[error("optional config file not found: {0}")]
+    OptionalFileMissing(PathBuf),
+
     #[error("failed to read config file '{path}': {source}")]
     ReadError {
         path: PathBuf,
@@ -19,14 +22,111 @@ pub enum ConfigError {
         source: toml::de::Error,
     },
 
+    #[error("'{}' is not UTF-8 or BOM-marked UTF-16 text", .0.display())]
+    Encoding(PathBuf),
+
+    #[error("invalid line {line} in '{}': expected KEY=value", .path.display())]
+    InvalidDotenv { path: PathBuf, line: usize },
+
+    #[error("invalid include in '{}': expected an array of file paths", .0.display())]
+    InvalidInclude(PathBuf),
+
+    #[error("circular include: {}", describe_chain(.0))]
+    CircularInclude(Vec<PathBuf>),
+
+    #[error("invalid config path: '{0}'")]
+    InvalidPath(String),
+
+    #[error("invalid root entry: expected a table, found {0}")]
+    InvalidRootEntry(&'static str),
+
+    #[error("expected {expected} config sources but {actual} were registered")]
+    SourceCountMismatch { expected: usize, actual: usize },
+
+    #[error("invalid glob pattern: {0}")]
+    InvalidGlob(String),
+
+    #[error("failed to serialize config: {0}")]
+    SerializeError(#[from] toml::ser::Error),
+
     #[error("failed to deserialize config: {0}")]
     DeserializeError(#[from] toml::de::Error),
 
+    #[error("failed to deserialize config at '{path}': {}", .source.message())]
+    DeserializeAt {
+        path: String,
+        source: Box<toml::de::Error>,
+    },
+
+    #[error("{source}\nmerged configuration:\n{table_dump}")]
+    DeserializeWithDump {
+        source: Box<ConfigError>,
+        table_dump: String,
+    },
+
+    #[error("invalid value '{value}' at '{path}': expected one of: {}", .expected.join(", "))]
+    InvalidEnumValue {
+        path: String,
+        value: String,
+        expected: Vec<String>,
+    },
+
+    #[error(
+        "expected a table at '{path}' but found a single value; set its fields \
+         individually (e.g. '{path}.<field>', or <PREFIX>__{}__<FIELD> in env)",
+        .path.to_uppercase().replace('.', "__")
+    )]
+    ExpectedTable { path: String },
+
+    #[error("config is nested deeper than the maximum depth of {0}")]
+    MaxDepthExceeded(usize),
+
+    #[error("integer '{value}' at '{path}' is out of range for a 64-bit signed integer")]
+    IntegerOverflow { path: String, value: String },
+
+    #[error("missing required field: {0}")]
+    MissingField(String),
+
+    #[error("missing required key: {0}")]
+    MissingRequiredKey(String),
+
+    #[error("required value is empty: {0}")]
+    EmptyRequiredValue(String),
+
+    #[error("section '{0}' is not in the base configuration")]
+    UnknownSection(String),
+
+    #[error("type conflict at '{path}': cannot replace {existing} with {incoming}")]
+    TypeConflict {
+        path: String,
+        existing: &'static str,
+        incoming: &'static str,
+    },
+
+    #[error("type of '{path}' changed from {from} to {to} between config layers")]
+    TypeChange {
+        path: String,
+        from: &'static str,
+        to: &'static str,
+    },
+
+    #[error("config validation failed: {0}")]
+    Validation(String),
+
+    #[error("config finalization failed: {0}")]
+    Finalize(String),
+
     #[error("circular reference detected in configuration")]
     CircularReference,
 
-    #[error("referenced path not found: {0}")]
-    ReferenceNotFound(String),
+    #[error("referenced path not found: {path}{}", describe_origin(.in_value_from))]
+    ReferenceNotFound {
+        path: String,
+        in_value_from: Option<PathBuf>,
+    },
+
+    #[error("unresolved reference left in value at '{0}'")]
+    UnresolvedReference(String),
 
     #[error("invalid reference path: {0}")]
     InvalidReferencePath(String),
@@ -34,6 +134,127 @@ pub enum ConfigError {
     #[error("cannot reference non-scalar value: {0}")]
     NonScalarReference(String),
 
-    #[error("unclosed reference (missing '}}')")]
-    UnclosedReference,
+    #[error("unclosed reference (no closing delimiter) at '{path}': {context}")]
+    UnclosedReference { path: String, context: String },
+
+    #[error("two keys resolve to the same name: '{0}'")]
+    KeyCollision(String),
+
+    #[error("{} configuration errors:{}", .0.len(), list_errors(.0))]
+    Multiple(Vec<ConfigError>),
+
+    #[cfg(feature = "async")]
+    #[error("async source needs build_async: {0}")]
+    AsyncSource(String),
+
+    #[cfg(feature = "watch")]
+    #[error("failed to watch config files: {0}")]
+    Watch(#[from] notify::Error),
+
+    #[cfg(feature = "watch")]
+    #[error("source {0} is not a file or glob source")]
+    NotAFileSource(usize),
+}
+
+// Stable checks for callers that can't match exhaustively. Errors wrapped in
+// `DeserializeWithDump` are looked through; `Multiple` matches if any does
+impl ConfigError {
+    pub fn is_not_found(&self) -> bool {
+        self.any(&|e| {
+            matches!(
+                e,
+                ConfigError::FileNotFound(_) | ConfigError::OptionalFileMissing(_)
+            )
+        })
+    }
+
+    pub fn is_parse_error(&self) -> bool {
+        self.any(&|e| {
+            matches!(
+                e,
+                ConfigError::ParseError { .. }
+                    | ConfigError::Encoding(_)
+                    | ConfigError::InvalidDotenv { .. }
+            )
+        })
+    }
+
+    pub fn is_reference_error(&self) -> bool {
+        self.any(&|e| {
+            matches!(
+                e,
+                ConfigError::CircularReference
+                    | ConfigError::ReferenceNotFound { .. }
+                    | ConfigError::UnresolvedReference(_)
+                    | ConfigError::InvalidReferencePath(_)
+                    | ConfigError::NonScalarReference(_)
+                    | ConfigError::UnclosedReference { .. }
+            )
+        })
+    }
+
+    // The file the error is about; for a circular include, the file whose
+    // include closed the cycle (the chain ends with the repeated file)
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ConfigError::FileNotFound(path)
+            | ConfigError::OptionalFileMissing(path)
+            | ConfigError::ReadError { path, .. }
+            | ConfigError::ParseError { path, .. }
+            | ConfigError::Encoding(path)
+            | ConfigError::InvalidDotenv { path, .. }
+            | ConfigError::InvalidInclude(path) => Some(path),
+            ConfigError::CircularInclude(chain) => chain.iter().rev().nth(1).map(PathBuf::as_path),
+            ConfigError::ReferenceNotFound { in_value_from, .. } => in_value_from.as_deref(),
+            ConfigError::DeserializeWithDump { source, .. } => source.path(),
+            _ => None,
+        }
+    }
+
+    // The dotted config key the error is about, as opposed to a file path
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            ConfigError::DeserializeAt { path, .. }
+            | ConfigError::InvalidEnumValue { path, .. }
+            | ConfigError::ExpectedTable { path }
+            | ConfigError::TypeConflict { path, .. }
+            | ConfigError::TypeChange { path, .. }
+            | ConfigError::UnclosedReference { path, .. }
+            | ConfigError::MissingField(path)
+            | ConfigError::MissingRequiredKey(path)
+            | ConfigError::EmptyRequiredValue(path)
+            | ConfigError::UnknownSection(path)
+            | ConfigError::UnresolvedReference(path)
+            | ConfigError::KeyCollision(path) => Some(path),
+            ConfigError::DeserializeWithDump { source, .. } => source.key(),
+            _ => None,
+        }
+    }
+
+    fn any(&self, check: &dyn Fn(&ConfigError) -> bool) -> bool {
+        match self {
+            ConfigError::DeserializeWithDump { source, .. } => source.any(check),
+            ConfigError::Multiple(errors) => errors.iter().any(|e| e.any(check)),
+            other => check(other),
+        }
+    }
+}
+
+fn describe_origin(origin: &Option<PathBuf>) -> String {
+    origin
+        .as_ref()
+        .map(|path| format!(" (in value from '{}')", path.display()))
+        .unwrap_or_default()
+}
+
+fn describe_chain(chain: &[PathBuf]) -> String {
+    chain
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+fn list_errors(errors: &[ConfigError]) -> String {
+    errors.iter().map(|e| format!("\n  - {e}")).collect()
 }
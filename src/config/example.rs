@@ -0,0 +1,187 @@
+use serde_json::Value;
+
+// Plain keys first, then one `[section]` per nested struct. Each key gets a
+// comment with its type and whether it's required; optional keys are
+// commented out so the example parses as-is
+pub fn render(root: &Value) -> String {
+    let mut out = String::new();
+    push_description(root, root, &mut out);
+    render_object(root, root, &mut Vec::new(), &mut out);
+    out
+}
+
+fn render_object(root: &Value, schema: &Value, path: &mut Vec<String>, out: &mut String) {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|keys| keys.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut sections = Vec::new();
+    for (name, property) in properties {
+        let (resolved, nullable) = resolve(root, property);
+        let optional = nullable || !required.contains(&name.as_str());
+        if resolved.get("properties").is_some() {
+            sections.push((name, property, resolved, optional));
+            continue;
+        }
+
+        separate(out);
+        push_description(property, resolved, out);
+        let status = if optional { "optional" } else { "required" };
+        out.push_str(&format!("# {}, {status}\n", describe(root, resolved)));
+        let line = format!("{} = {}\n", key(name), placeholder(resolved));
+        if optional {
+            out.push_str("# ");
+        }
+        out.push_str(&line);
+    }
+
+    for (name, property, resolved, optional) in sections {
+        path.push(name.clone());
+        separate(out);
+        push_description(property, resolved, out);
+        if optional {
+            out.push_str("# optional section\n");
+        }
+        let header: Vec<String> = path.iter().map(|segment| key(segment)).collect();
+        out.push_str(&format!("[{}]\n", header.join(".")));
+        render_object(root, resolved, path, out);
+        path.pop();
+    }
+}
+
+// Follows `$ref`s into `$defs` and unwraps `Option<T>`, which schemars
+// writes as `anyOf: [T, null]` or `type: [T, "null"]`
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> (&'a Value, bool) {
+    if let Some(variants) = schema.get("anyOf").and_then(Value::as_array) {
+        let mut non_null = variants.iter().filter(|variant| !is_null(variant));
+        if let (Some(inner), None, 2) = (non_null.next(), non_null.next(), variants.len()) {
+            return (resolve(root, inner).0, true);
+        }
+    }
+    if let Some(name) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/$defs/"))
+    {
+        if let Some(definition) = root.get("$defs").and_then(|defs| defs.get(name)) {
+            return resolve(root, definition);
+        }
+    }
+    let nullable = match schema.get("type") {
+        Some(Value::Array(types)) => types.iter().any(|t| t == "null"),
+        _ => false,
+    };
+    (schema, nullable)
+}
+
+fn is_null(schema: &Value) -> bool {
+    schema.get("type").and_then(Value::as_str) == Some("null")
+}
+
+// The schema's type with `null` dropped, since optionality is shown separately
+fn type_of(schema: &Value) -> Option<&str> {
+    match schema.get("type")? {
+        Value::String(t) => Some(t),
+        Value::Array(types) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|t| *t != "null"),
+        _ => None,
+    }
+}
+
+fn describe(root: &Value, schema: &Value) -> String {
+    if let Some(variants) = schema.get("enum").and_then(Value::as_array) {
+        let names: Vec<String> = variants.iter().map(Value::to_string).collect();
+        return format!("one of: {}", names.join(", "));
+    }
+    let element = |key: &str| {
+        schema
+            .get(key)
+            .filter(|items| items.is_object())
+            .map(|items| describe(root, resolve(root, items).0))
+    };
+    match type_of(schema) {
+        Some("array") => match element("items") {
+            Some(items) => format!("array of {items}"),
+            None => "array".to_string(),
+        },
+        Some("object") => match element("additionalProperties") {
+            Some(values) => format!("table of {values}"),
+            None => "table".to_string(),
+        },
+        Some("number") => "float".to_string(),
+        Some(other) => other.to_string(),
+        None if schema.get("properties").is_some() => "table".to_string(),
+        None => "any".to_string(),
+    }
+}
+
+// A TOML literal for the key: the schema's default if it has one, otherwise
+// an empty value of the right type
+fn placeholder(schema: &Value) -> String {
+    if let Some(default) = schema.get("default").filter(|default| !default.is_null()) {
+        if let Ok(value) = toml::Value::try_from(default) {
+            return value.to_string();
+        }
+    }
+    if let Some(first) = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|variants| variants.first())
+    {
+        return first.to_string();
+    }
+    match type_of(schema) {
+        Some("integer") => "0",
+        Some("number") => "0.0",
+        Some("boolean") => "false",
+        Some("array") => "[]",
+        Some("object") => "{}",
+        _ => "\"\"",
+    }
+    .to_string()
+}
+
+// A field's own doc comment wins over the one on the type it refers to
+fn push_description(property: &Value, resolved: &Value, out: &mut String) {
+    let description = property
+        .get("description")
+        .or_else(|| resolved.get("description"))
+        .and_then(Value::as_str);
+    if let Some(description) = description {
+        for line in description.lines() {
+            out.push_str(format!("# {line}").trim_end());
+            out.push('\n');
+        }
+    }
+}
+
+// Blank line between entries, but not right after a section header
+fn separate(out: &mut String) {
+    let last_line = out
+        .trim_end_matches('\n')
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default();
+    if !out.is_empty() && !last_line.starts_with('[') {
+        out.push('\n');
+    }
+}
+
+// Bare keys stay as-is; anything else is written as a quoted TOML string
+fn key(segment: &str) -> String {
+    let bare = !segment.is_empty()
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    match bare {
+        true => segment.to_string(),
+        false => toml::Value::String(segment.to_string()).to_string(),
+    }
+}
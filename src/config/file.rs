@@ -1,8 +1,18 @@
 use std::path::{Path, PathBuf};
 
+use toml::{Table, Value};
+
+use super::merge::{merge_at_path, MergeRules};
 use super::source::{ConfigEntry, ConfigSource};
+use super::trace;
 use super::ConfigError;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Toml,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileSource {
     path: PathBuf,
@@ -21,21 +31,48 @@ impl FileSource {
 impl ConfigSource for FileSource {
     fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
         match load_config_file(&self.path, self.required)? {
-            Some(table) => Ok(vec![ConfigEntry::root(table)]),
-            None => Ok(vec![]),
+            Some(table) => {
+                trace::file_loaded(&self.path);
+                Ok(vec![ConfigEntry::root(table)])
+            }
+            None => {
+                trace::file_skipped(&self.path);
+                Ok(vec![])
+            }
         }
     }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn is_required(&self) -> bool {
+        self.required
+    }
+
+    fn describe(&self) -> String {
+        let required = if self.required {
+            "required"
+        } else {
+            "optional"
+        };
+        format!("file:{} ({required})", self.path.display())
+    }
 }
 
-fn load_config_file(path: &Path, required: bool) -> Result<Option<toml::Table>, ConfigError> {
-    match std::fs::read_to_string(path) {
-        Ok(contents) => {
-            let table = toml::from_str(&contents).map_err(|e| ConfigError::ParseError {
-                path: path.to_path_buf(),
-                source: e,
-            })?;
-            Ok(Some(table))
+fn load_config_file(path: &Path, required: bool) -> Result<Option<Table>, ConfigError> {
+    match read_config_file(path, required)? {
+        Some(table) => {
+            let mut chain = vec![include_key(path)];
+            Ok(Some(expand_includes(path, table, &mut chain)?))
         }
+        None => Ok(None),
+    }
+}
+
+fn read_config_file(path: &Path, required: bool) -> Result<Option<Table>, ConfigError> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(parse(bytes, Format::Toml, path)?)),
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
             if required {
                 Err(ConfigError::FileNotFound(path.to_path_buf()))
@@ -49,3 +86,87 @@ fn load_config_file(path: &Path, required: bool) -> Result<Option<toml::Table>,
         }),
     }
 }
+
+// `path` only labels errors; it may be synthetic, like `<reader>`
+pub(super) fn parse(bytes: Vec<u8>, format: Format, path: &Path) -> Result<Table, ConfigError> {
+    let contents = decode(bytes).ok_or_else(|| ConfigError::Encoding(path.to_path_buf()))?;
+    match format {
+        Format::Toml => toml::from_str(&contents).map_err(|e| ConfigError::ParseError {
+            path: path.to_path_buf(),
+            source: e,
+        }),
+    }
+}
+
+// Editors on Windows often save with a BOM; UTF-16 is only accepted when
+// marked by one, since it can't be told apart from UTF-8 otherwise
+pub(super) fn decode(bytes: Vec<u8>) -> Option<String> {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        if !bytes.len().is_multiple_of(2) {
+            return None;
+        }
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+        String::from_utf16(&units).ok()
+    };
+
+    match bytes.as_slice() {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8(rest.to_vec()).ok(),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        _ => String::from_utf8(bytes).ok(),
+    }
+}
+
+// Included files are merged in order beneath the including file's own keys;
+// `chain` holds the files currently being expanded, to detect cycles
+fn expand_includes(
+    path: &Path,
+    mut table: Table,
+    chain: &mut Vec<PathBuf>,
+) -> Result<Table, ConfigError> {
+    let Some(include) = table.remove("include") else {
+        return Ok(table);
+    };
+    let invalid = || ConfigError::InvalidInclude(path.to_path_buf());
+    let Value::Array(files) = include else {
+        return Err(invalid());
+    };
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    // Unset sentinels are kept as values here so they still apply to keys
+    // set by earlier sources once the whole file is merged
+    let rules = MergeRules {
+        unset: None,
+        ..MergeRules::default()
+    };
+    let mut merged = Table::new();
+
+    for file in files {
+        let file = base_dir.join(normalize(file.as_str().ok_or_else(invalid)?));
+        let key = include_key(&file);
+        if chain.contains(&key) {
+            let mut cycle = chain.clone();
+            cycle.push(key);
+            return Err(ConfigError::CircularInclude(cycle));
+        }
+
+        let included = read_config_file(&file, true)?.unwrap_or_default();
+        chain.push(key);
+        let included = expand_includes(&file, included, chain)?;
+        chain.pop();
+        merge_at_path(&mut merged, &[], Value::Table(included), &rules)?;
+    }
+
+    merge_at_path(&mut merged, &[], Value::Table(table), &rules)?;
+    Ok(merged)
+}
+
+// Rebuilds the path from its components so include paths written with `/`
+// render with the platform's separator, like the including file's path
+fn normalize(path: &str) -> PathBuf {
+    Path::new(path).components().collect()
+}
+
+fn include_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
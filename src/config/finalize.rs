@@ -0,0 +1,3 @@
+pub trait Finalize {
+    fn finalize(&mut self) -> Result<(), String>;
+}
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use super::file::FileSource;
+use super::source::{ConfigEntry, ConfigSource};
+use super::ConfigError;
+
+#[derive(Debug, Clone)]
+pub struct GlobSource {
+    pattern: String,
+    required: bool,
+}
+
+impl GlobSource {
+    pub fn new(pattern: impl Into<String>, required: bool) -> Self {
+        Self {
+            pattern: pattern.into(),
+            required,
+        }
+    }
+
+    pub fn paths(&self) -> Result<Vec<PathBuf>, ConfigError> {
+        let matches = glob::glob(&self.pattern)
+            .map_err(|e| ConfigError::InvalidGlob(format!("{}: {e}", self.pattern)))?;
+
+        let mut paths = Vec::new();
+        for entry in matches {
+            let path = entry.map_err(|e| ConfigError::ReadError {
+                path: e.path().to_path_buf(),
+                source: e.into(),
+            })?;
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+
+        // Component-wise ordering is independent of the platform's separator
+        paths.sort();
+
+        if paths.is_empty() && self.required {
+            return Err(ConfigError::FileNotFound(PathBuf::from(&self.pattern)));
+        }
+
+        Ok(paths)
+    }
+
+    pub fn files(&self) -> Result<Vec<FileSource>, ConfigError> {
+        Ok(self
+            .paths()?
+            .into_iter()
+            .map(|path| FileSource::new(path, true))
+            .collect())
+    }
+}
+
+impl ConfigSource for GlobSource {
+    fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+        let mut entries = Vec::new();
+        for file in self.files()? {
+            entries.extend(file.entries()?);
+        }
+        Ok(entries)
+    }
+
+    fn is_required(&self) -> bool {
+        self.required
+    }
+
+    fn describe(&self) -> String {
+        let required = if self.required {
+            "required"
+        } else {
+            "optional"
+        };
+        format!("glob:{} ({required})", self.pattern)
+    }
+}
@@ -0,0 +1,44 @@
+use serde_json::Value as Json;
+use toml::{Table, Value};
+
+use super::ConfigError;
+
+// TOML has no null: null object members and array elements are dropped, so a
+// null key reads as absent
+pub fn json_to_table(json: Json) -> Result<Table, ConfigError> {
+    match json {
+        Json::Object(members) => Ok(members
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, json_to_toml(value)?)))
+            .collect()),
+        other => Err(ConfigError::InvalidRootEntry(json_type(&other))),
+    }
+}
+
+// `None` for null, which TOML can't hold
+pub(super) fn json_to_toml(json: Json) -> Option<Value> {
+    Some(match json {
+        Json::Null => return None,
+        Json::Bool(b) => Value::Boolean(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            // Above i64::MAX, like an env value: kept as its text
+            None if n.is_u64() => Value::String(n.to_string()),
+            None => Value::Float(n.as_f64()?),
+        },
+        Json::String(s) => Value::String(s),
+        Json::Array(items) => Value::Array(items.into_iter().filter_map(json_to_toml).collect()),
+        Json::Object(_) => Value::Table(json_to_table(json).ok()?),
+    })
+}
+
+fn json_type(json: &Json) -> &'static str {
+    match json {
+        Json::Null => "null",
+        Json::Bool(_) => "boolean",
+        Json::Number(_) => "number",
+        Json::String(_) => "string",
+        Json::Array(_) => "array",
+        Json::Object(_) => "object",
+    }
+}
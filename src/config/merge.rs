@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use toml::{Table, Value};
+
+use super::ConfigError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    #[default]
+    Replace,
+    Append,
+    // Like `Append`, skipping overlay elements equal to one already present
+    AppendUnique,
+}
+
+const DEFAULT_UNSET_SENTINEL: &str = "__unset__";
+
+// The global array strategy plus overrides for exact dotted locations, arrays
+// of tables upserted by a key field, and the string value that removes a key
+// instead of setting it
+#[derive(Debug, Clone)]
+pub struct MergeRules {
+    pub arrays: ArrayMerge,
+    pub paths: HashMap<Vec<String>, ArrayMerge>,
+    pub keys: HashMap<Vec<String>, String>,
+    pub unset: Option<String>,
+}
+
+impl Default for MergeRules {
+    fn default() -> Self {
+        Self {
+            arrays: ArrayMerge::default(),
+            paths: HashMap::new(),
+            keys: HashMap::new(),
+            unset: Some(DEFAULT_UNSET_SENTINEL.to_string()),
+        }
+    }
+}
+
+impl MergeRules {
+    fn strategy_at(&self, location: &[String]) -> ArrayMerge {
+        self.paths.get(location).copied().unwrap_or(self.arrays)
+    }
+
+    fn key_at(&self, location: &[String]) -> Option<&str> {
+        self.keys.get(location).map(String::as_str)
+    }
+
+    fn is_unset(&self, value: &Value) -> bool {
+        matches!((value, &self.unset), (Value::String(s), Some(unset)) if s == unset)
+    }
+}
+
+pub fn merge_at_path(
+    table: &mut Table,
+    path: &[String],
+    value: Value,
+    rules: &MergeRules,
+) -> Result<(), ConfigError> {
+    if path.is_empty() {
+        // Root-level merge: only a table can be merged into the root
+        return match value {
+            Value::Table(overlay) => {
+                deep_merge(table, overlay, &mut Vec::new(), rules);
+                Ok(())
+            }
+            other => Err(ConfigError::InvalidRootEntry(other.type_str())),
+        };
+    }
+
+    merge_in_table(table, path, value, &mut Vec::new(), rules);
+    Ok(())
+}
+
+// Navigates to the target location, creating intermediate tables as needed;
+// unsetting a key under a missing table is a no-op, so none are created
+fn merge_in_table(
+    table: &mut Table,
+    path: &[String],
+    value: Value,
+    location: &mut Vec<String>,
+    rules: &MergeRules,
+) {
+    let Some((key, rest)) = path.split_first() else {
+        return;
+    };
+    if rest.is_empty() {
+        // At final key: merge or replace depending on types
+        merge_value(table, key.clone(), value, location, rules);
+        return;
+    }
+    if rules.is_unset(&value) && !table.contains_key(key) {
+        return;
+    }
+
+    location.push(key.clone());
+    let child = table
+        .entry(key.clone())
+        .or_insert_with(|| Value::Table(Table::new()));
+    merge_below(child, rest, value, location, rules);
+    location.pop();
+}
+
+// A numeric segment indexes into an existing array; any other non-table
+// value in the way is replaced by a table
+fn merge_below(
+    slot: &mut Value,
+    path: &[String],
+    value: Value,
+    location: &mut Vec<String>,
+    rules: &MergeRules,
+) {
+    match slot {
+        Value::Array(items) if path[0].parse::<usize>().is_ok() => {
+            merge_in_array(items, path, value, location, rules);
+        }
+        Value::Table(table) => merge_in_table(table, path, value, location, rules),
+        _ if rules.is_unset(&value) => {}
+        _ => {
+            let mut table = Table::new();
+            merge_in_table(&mut table, path, value, location, rules);
+            *slot = Value::Table(table);
+        }
+    }
+}
+
+// Indices past the end extend the array, padding any gap with empty tables
+fn merge_in_array(
+    items: &mut Vec<Value>,
+    path: &[String],
+    value: Value,
+    location: &mut Vec<String>,
+    rules: &MergeRules,
+) {
+    let (segment, rest) = path.split_first().expect("path is not empty");
+    let index: usize = segment.parse().expect("segment is an index");
+    if rules.is_unset(&value) {
+        if index >= items.len() {
+            return;
+        }
+        if rest.is_empty() {
+            items.remove(index);
+            return;
+        }
+    }
+    while items.len() <= index {
+        items.push(Value::Table(Table::new()));
+    }
+
+    location.push(segment.clone());
+    match (&mut items[index], value) {
+        (item, value) if !rest.is_empty() => merge_below(item, rest, value, location, rules),
+        (Value::Table(base), Value::Table(overlay)) => deep_merge(base, overlay, location, rules),
+        (Value::Array(base), Value::Array(overlay))
+            if rules.strategy_at(location) != ArrayMerge::Replace =>
+        {
+            append(base, overlay, rules.strategy_at(location));
+        }
+        (item, value) => *item = value,
+    }
+    location.pop();
+}
+
+// Equality is full `Value` equality, so tables must match in every field;
+// duplicates within the overlay itself are skipped too
+fn append(base: &mut Vec<Value>, overlay: Vec<Value>, strategy: ArrayMerge) {
+    for item in overlay {
+        if strategy == ArrayMerge::AppendUnique && base.contains(&item) {
+            continue;
+        }
+        base.push(item);
+    }
+}
+
+pub fn check_type_conflict(
+    table: &Table,
+    path: &[String],
+    value: &Value,
+) -> Result<(), ConfigError> {
+    let Some(first) = path.first() else {
+        return Ok(());
+    };
+    let mut next = table.get(first);
+
+    for depth in 0..path.len() {
+        let Some(existing) = next else {
+            return Ok(());
+        };
+        let is_leaf = depth + 1 == path.len();
+
+        match existing {
+            Value::Table(nested) if !is_leaf => next = nested.get(&path[depth + 1]),
+            // Indices past the end are appended, so they can't conflict
+            Value::Array(items) if !is_leaf && path[depth + 1].parse::<usize>().is_ok() => {
+                next = path[depth + 1]
+                    .parse()
+                    .ok()
+                    .and_then(|i: usize| items.get(i));
+            }
+            // A table merges key by key, so each of its keys is checked too
+            Value::Table(_) if is_leaf && value.is_table() => {
+                for (key, child) in value.as_table().into_iter().flatten() {
+                    let mut child_path = path.to_vec();
+                    child_path.push(key.clone());
+                    check_type_conflict(table, &child_path, child)?;
+                }
+                return Ok(());
+            }
+            existing if is_leaf && !existing.is_table() && !value.is_table() => return Ok(()),
+            existing => {
+                return Err(ConfigError::TypeConflict {
+                    path: path[..=depth].join("."),
+                    existing: existing.type_str(),
+                    incoming: if is_leaf { value.type_str() } else { "table" },
+                })
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Scalars in `value` that would replace a scalar of another type once merged
+// at `path`, as (dotted key, existing type, incoming type). Tables and arrays
+// are skipped; replacing those is `check_type_conflict`'s concern
+pub fn type_changes(
+    table: &Table,
+    path: &[String],
+    value: &Value,
+    rules: &MergeRules,
+) -> Vec<(String, &'static str, &'static str)> {
+    let mut changes = Vec::new();
+    collect_type_changes(table, &mut path.to_vec(), value, rules, &mut changes);
+    changes
+}
+
+fn collect_type_changes(
+    table: &Table,
+    path: &mut Vec<String>,
+    value: &Value,
+    rules: &MergeRules,
+    changes: &mut Vec<(String, &'static str, &'static str)>,
+) {
+    match value {
+        Value::Table(overlay) => {
+            for (key, value) in overlay {
+                path.push(key.clone());
+                collect_type_changes(table, path, value, rules, changes);
+                path.pop();
+            }
+        }
+        Value::Array(_) => {}
+        value if rules.is_unset(value) => {}
+        value => {
+            let existing = value_at(table, path)
+                .filter(|existing| !existing.is_table() && !existing.is_array());
+            if let Some(existing) = existing {
+                if existing.type_str() != value.type_str() {
+                    changes.push((path.join("."), existing.type_str(), value.type_str()));
+                }
+            }
+        }
+    }
+}
+
+fn value_at<'a>(table: &'a Table, path: &[String]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(table.get(first)?, |value, segment| match value {
+            Value::Table(nested) => nested.get(segment),
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+fn deep_merge(base: &mut Table, overlay: Table, location: &mut Vec<String>, rules: &MergeRules) {
+    for (key, value) in overlay {
+        merge_value(base, key, value, location, rules);
+    }
+}
+
+// `location` is the dotted path of `base`, used to pick the array strategy
+fn merge_value(
+    base: &mut Table,
+    key: String,
+    value: Value,
+    location: &mut Vec<String>,
+    rules: &MergeRules,
+) {
+    location.push(key.clone());
+    match (base.get_mut(&key), value) {
+        (_, value) if rules.is_unset(&value) => {
+            base.remove(&key);
+        }
+        (Some(Value::Table(base_table)), Value::Table(overlay_table)) => {
+            deep_merge(base_table, overlay_table, location, rules);
+        }
+        (Some(Value::Array(base_array)), Value::Array(overlay_array))
+            if rules.key_at(location).is_some() =>
+        {
+            upsert(base_array, overlay_array, location, rules);
+        }
+        // Upserted into an empty array so duplicates within one layer merge too
+        (_, Value::Array(overlay_array)) if rules.key_at(location).is_some() => {
+            let mut items = Vec::new();
+            upsert(&mut items, overlay_array, location, rules);
+            base.insert(key, Value::Array(items));
+        }
+        (Some(Value::Array(base_array)), Value::Array(overlay_array))
+            if rules.strategy_at(location) != ArrayMerge::Replace =>
+        {
+            append(base_array, overlay_array, rules.strategy_at(location));
+        }
+        // Merged into an empty table rather than inserted so that sentinels
+        // inside a brand-new table are stripped; a table that held nothing
+        // but sentinels isn't created at all
+        (_, Value::Table(overlay_table)) => {
+            let had_keys = !overlay_table.is_empty();
+            let mut fresh = Table::new();
+            deep_merge(&mut fresh, overlay_table, location, rules);
+            if !(had_keys && fresh.is_empty()) {
+                base.insert(key, Value::Table(fresh));
+            }
+        }
+        (_, value) => {
+            base.insert(key, value);
+        }
+    }
+    location.pop();
+}
+
+// Tables whose key field matches an element already in `base` are deep-merged
+// into it; everything else, including tables without the field, is appended
+fn upsert(
+    base: &mut Vec<Value>,
+    overlay: Vec<Value>,
+    location: &mut Vec<String>,
+    rules: &MergeRules,
+) {
+    let field = rules.key_at(location).expect("location has a key field");
+    for item in overlay {
+        let existing = item.get(field).and_then(|id| {
+            base.iter()
+                .position(|element| element.get(field) == Some(id))
+        });
+        match (existing, item) {
+            (Some(index), Value::Table(overlay)) => {
+                location.push(index.to_string());
+                if let Value::Table(element) = &mut base[index] {
+                    deep_merge(element, overlay, location, rules);
+                }
+                location.pop();
+            }
+            (_, item) => base.push(item),
+        }
+    }
+}
@@ -1,10 +1,45 @@
 mod builder;
+mod case;
+pub mod coerce;
+mod diff;
+mod dotenv;
 mod env;
 mod error;
+#[cfg(feature = "schemars")]
+mod example;
 mod file;
+mod finalize;
+mod glob;
+#[cfg(feature = "json")]
+mod json;
+mod merge;
+mod overrides;
+#[cfg(feature = "async")]
+mod remote;
+mod report;
 mod resolve;
 mod source;
+mod trace;
+#[cfg(feature = "watch")]
+mod watch;
 
 pub use builder::Config;
+pub use case::KeyCase;
+pub use coerce::coerce_value as default_coerce_value;
+pub use diff::{diff, Change, ConfigDiff};
+pub use dotenv::DotenvSource;
+pub use env::{Coercion, EnvOptions, EnvSource};
 pub use error::ConfigError;
-pub use source::{ConfigEntry, ConfigSource};
+pub use file::{FileSource, Format};
+pub use finalize::Finalize;
+pub use glob::GlobSource;
+pub use merge::ArrayMerge;
+pub use overrides::{OverrideSource, VecSource};
+#[cfg(feature = "async")]
+pub use remote::AsyncConfigSource;
+pub use report::{BuildReport, KeyOverride, SourceSummary, TypeChange};
+pub(crate) use resolve::lookup_value;
+pub use resolve::ReferenceMode;
+pub use source::{keys, CloneSource, ConfigEntry, ConfigSource, Origin};
+#[cfg(feature = "watch")]
+pub use watch::{WatchedConfig, Watcher};
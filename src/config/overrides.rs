@@ -0,0 +1,67 @@
+use toml::Value;
+
+use super::source::{split_path, ConfigEntry, ConfigSource};
+use super::ConfigError;
+
+#[derive(Debug, Clone, Default)]
+pub struct OverrideSource {
+    overrides: Vec<(String, Value)>,
+}
+
+impl OverrideSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, path: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.overrides.push((path.into(), value.into()));
+        self
+    }
+}
+
+impl ConfigSource for OverrideSource {
+    fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+        self.overrides
+            .iter()
+            .map(|(path, value)| {
+                let segments = split_path(path);
+                if segments.iter().any(String::is_empty) {
+                    return Err(ConfigError::InvalidPath(path.clone()));
+                }
+                Ok(ConfigEntry::at_path(segments, value.clone()))
+            })
+            .collect()
+    }
+
+    // Paths only: override values may be secrets
+    fn describe(&self) -> String {
+        let paths: Vec<&str> = self
+            .overrides
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        format!("overrides:{}", paths.join(","))
+    }
+}
+
+// Entries computed elsewhere, merged in the order they were given
+#[derive(Debug, Clone, Default)]
+pub struct VecSource {
+    entries: Vec<ConfigEntry>,
+}
+
+impl VecSource {
+    pub fn new(entries: Vec<ConfigEntry>) -> Self {
+        Self { entries }
+    }
+}
+
+impl ConfigSource for VecSource {
+    fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+        Ok(self.entries.clone())
+    }
+
+    fn describe(&self) -> String {
+        format!("entries:{}", self.entries.len())
+    }
+}
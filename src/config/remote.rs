@@ -0,0 +1,82 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+use super::source::{ConfigEntry, ConfigSource, Origin};
+use super::ConfigError;
+
+type FetchFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<FetchedSource, ConfigError>> + Send + 'a>>;
+
+// Implementors can write `async fn entries(&self)`; the `Send` bound keeps
+// `build_async` usable from multi-threaded runtimes
+pub trait AsyncConfigSource: Send + Sync + std::fmt::Debug {
+    fn entries(&self) -> impl Future<Output = Result<Vec<ConfigEntry>, ConfigError>> + Send;
+
+    fn describe(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn origin(&self) -> Origin {
+        Origin::Override
+    }
+}
+
+// Every sync source is an async one that is ready immediately
+impl<S: ConfigSource> AsyncConfigSource for S {
+    fn entries(&self) -> impl Future<Output = Result<Vec<ConfigEntry>, ConfigError>> + Send {
+        std::future::ready(ConfigSource::entries(self))
+    }
+
+    fn describe(&self) -> String {
+        ConfigSource::describe(self)
+    }
+
+    fn origin(&self) -> Origin {
+        ConfigSource::origin(self)
+    }
+}
+
+// `AsyncConfigSource` returns an opaque future, so it can't be boxed as a
+// trait object; the builder stores sources through this instead
+pub(super) trait DynAsyncSource: Send + Sync + std::fmt::Debug {
+    fn fetch(&self) -> FetchFuture<'_>;
+}
+
+impl<S: AsyncConfigSource> DynAsyncSource for S {
+    fn fetch(&self) -> FetchFuture<'_> {
+        Box::pin(async move {
+            Ok(FetchedSource {
+                description: AsyncConfigSource::describe(self),
+                origin: AsyncConfigSource::origin(self),
+                entries: AsyncConfigSource::entries(self).await?,
+            })
+        })
+    }
+}
+
+// An async source after `build_async` awaited it, merged like any sync source
+#[derive(Debug, Clone)]
+pub(super) struct FetchedSource {
+    description: String,
+    origin: Origin,
+    entries: Vec<ConfigEntry>,
+}
+
+impl ConfigSource for FetchedSource {
+    fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError> {
+        Ok(self.entries.clone())
+    }
+
+    fn path(&self) -> Option<&Path> {
+        self.origin.path()
+    }
+
+    fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    fn origin(&self) -> Origin {
+        self.origin.clone()
+    }
+}
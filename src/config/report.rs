@@ -0,0 +1,159 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildReport {
+    pub(super) sources: Vec<SourceSummary>,
+    pub(super) skipped_files: Vec<PathBuf>,
+    pub(super) overrides: Vec<KeyOverride>,
+    pub(super) empty_sources: Vec<String>,
+    pub(super) unresolved_references: Vec<String>,
+    pub(super) type_changes: Vec<TypeChange>,
+    pub(super) integer_overflows: Vec<String>,
+}
+
+impl BuildReport {
+    pub fn sources(&self) -> &[SourceSummary] {
+        &self.sources
+    }
+
+    pub fn skipped_files(&self) -> &[PathBuf] {
+        &self.skipped_files
+    }
+
+    pub fn overrides(&self) -> &[KeyOverride] {
+        &self.overrides
+    }
+
+    pub fn empty_sources(&self) -> &[String] {
+        &self.empty_sources
+    }
+
+    pub fn unresolved_references(&self) -> &[String] {
+        &self.unresolved_references
+    }
+
+    pub fn type_changes(&self) -> &[TypeChange] {
+        &self.type_changes
+    }
+
+    pub fn integer_overflows(&self) -> &[String] {
+        &self.integer_overflows
+    }
+
+    // Source summaries are always recorded, so they don't count as events
+    pub fn is_empty(&self) -> bool {
+        self.skipped_files.is_empty()
+            && self.overrides.is_empty()
+            && self.empty_sources.is_empty()
+            && self.unresolved_references.is_empty()
+            && self.type_changes.is_empty()
+            && self.integer_overflows.is_empty()
+    }
+
+    // A file-backed source with no entries whose file is missing was skipped
+    // as optional; anything else simply had nothing to contribute
+    pub(super) fn record_source(
+        &mut self,
+        description: String,
+        entries: usize,
+        origin: Option<&Path>,
+    ) {
+        if entries == 0 {
+            match origin {
+                Some(path) if !path.exists() => self.skipped_files.push(path.to_path_buf()),
+                _ => self.empty_sources.push(description.clone()),
+            }
+        }
+        self.sources.push(SourceSummary {
+            description,
+            entries,
+        });
+    }
+}
+
+impl fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() && self.sources.is_empty() {
+            return write!(f, "build report: no events");
+        }
+
+        write!(f, "build report:")?;
+        for source in &self.sources {
+            write!(f, "\n  - {source}")?;
+        }
+        for path in &self.skipped_files {
+            write!(f, "\n  - skipped optional file '{}'", path.display())?;
+        }
+        for o in &self.overrides {
+            write!(f, "\n  - {o}")?;
+        }
+        for source in &self.empty_sources {
+            write!(f, "\n  - source contributed no entries: {source}")?;
+        }
+        for path in &self.unresolved_references {
+            write!(
+                f,
+                "\n  - missing reference '${{{path}}}' replaced with an empty string"
+            )?;
+        }
+        for change in &self.type_changes {
+            write!(f, "\n  - {change}")?;
+        }
+        for path in &self.integer_overflows {
+            write!(
+                f,
+                "\n  - '{path}' is too large for an integer and was kept as a string"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOverride {
+    pub path: String,
+    pub previous: PathBuf,
+    pub current: PathBuf,
+}
+
+impl fmt::Display for KeyOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' set in '{}' overridden by '{}'",
+            self.path,
+            self.previous.display(),
+            self.current.display()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeChange {
+    pub path: String,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+impl fmt::Display for TypeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' changed type from {} to {}",
+            self.path, self.from, self.to
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceSummary {
+    pub description: String,
+    pub entries: usize,
+}
+
+impl fmt::Display for SourceSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} entries", self.description, self.entries)
+    }
+}
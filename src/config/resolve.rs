@@ -1,38 +1,169 @@
+use super::case::{normalize_path, KeyCase};
+use super::source::{split_path, Provenance};
+use super::trace;
 use super::ConfigError;
+use std::collections::HashMap;
+use toml::value::Datetime;
 use toml::{Table, Value};
 
 const MAX_ITERATIONS: usize = 100;
+// Characters of an unclosed reference quoted in its error, `${` included
+const CONTEXT_LEN: usize = 32;
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReferenceMode {
+    #[default]
+    Strict,
+    // Missing references become empty strings instead of failing the build
+    Lenient,
+}
+
+// The reference markers; the first char of `open`, doubled, escapes it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delimiters {
+    open: String,
+    close: String,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self::new("${", "}")
+    }
+}
+
+impl Delimiters {
+    pub fn new(open: &str, close: &str) -> Self {
+        assert!(
+            !open.is_empty() && !close.is_empty(),
+            "delimiters must not be empty"
+        );
+        let escape = open.chars().next().expect("open is not empty");
+        assert!(
+            !open[escape.len_utf8()..].starts_with(escape),
+            "open delimiter must not start with its own escape"
+        );
+        Self {
+            open: open.to_string(),
+            close: close.to_string(),
+        }
+    }
+
+    fn escape(&self) -> char {
+        self.open.chars().next().expect("open is not empty")
+    }
+}
+
+// How a merged table's references are resolved, as set on the builder
+pub struct ResolveOptions<'a> {
+    pub mode: ReferenceMode,
+    pub max_depth: usize,
+    pub keys: bool,
+    pub delimiters: &'a Delimiters,
+    pub key_case: Option<KeyCase>,
+    // `env:` alternatives read this instead of the live process environment
+    pub env: Option<&'a HashMap<String, String>>,
+}
+
+// Returns the paths of missing references that lenient mode replaced
+pub fn resolve_references(
+    table: &mut Table,
+    provenance: &Provenance,
+    options: &ResolveOptions,
+) -> Result<Vec<String>, ConfigError> {
+    let ResolveOptions {
+        mode,
+        max_depth,
+        keys,
+        delimiters,
+        key_case,
+        env,
+    } = *options;
+    let mut dangling = Vec::new();
+    let mut total = 0;
 
-pub fn resolve_references(table: &mut Table) -> Result<(), ConfigError> {
     for _ in 0..MAX_ITERATIONS {
         let snapshot = table.clone();
-        let substitutions = resolve_pass(table, &snapshot)?;
+        let mut key_path = Vec::new();
+        let mut resolver = Resolver {
+            root: &snapshot,
+            mode,
+            max_depth,
+            delimiters,
+            key_case,
+            env,
+            dangling: &mut dangling,
+        };
+        let substitutions = resolve_pass(table, &mut resolver, &mut key_path)
+            .map_err(|e| attach_origin(e, &key_path, provenance))?;
+        total += substitutions;
         if substitutions == 0 {
-            return Ok(());
+            let mut key_path = Vec::new();
+            if keys {
+                let snapshot = table.clone();
+                let mut resolver = Resolver {
+                    root: &snapshot,
+                    mode,
+                    max_depth,
+                    delimiters,
+                    key_case,
+                    env,
+                    dangling: &mut dangling,
+                };
+                total += resolve_keys(table, &mut resolver, &mut key_path)
+                    .map_err(|e| attach_origin(e, &key_path, provenance))?;
+            }
+            // Escapes survive every pass so copied `$${...}` text is never
+            // re-resolved; only now are they turned into literal `$`
+            unescape_table(table, delimiters, &mut key_path)?;
+            trace::references_resolved(total);
+            return Ok(dangling);
         }
     }
 
     Err(ConfigError::CircularReference)
 }
 
-fn resolve_pass(table: &mut Table, root: &Table) -> Result<usize, ConfigError> {
+// A single pass once every value has settled, so a key sees resolved values
+// but never another key's resolved name. Keys are unescaped here as well
+fn resolve_keys(
+    table: &mut Table,
+    resolver: &mut Resolver,
+    key_path: &mut Vec<String>,
+) -> Result<usize, ConfigError> {
     let mut count = 0;
 
-    for (_key, value) in table.iter_mut() {
-        count += resolve_value(value, root)?;
+    for (key, mut value) in std::mem::take(table) {
+        key_path.push(key.clone());
+        count += resolve_keys_in(&mut value, resolver, key_path)?;
+        let mut resolved = key;
+        count += resolve_string(&mut resolved, resolver, key_path)?;
+        let resolved = unescape(&resolved, resolver.delimiters, key_path)?;
+        key_path.pop();
+
+        if table.contains_key(&resolved) {
+            key_path.push(resolved);
+            return Err(ConfigError::KeyCollision(key_path.join(".")));
+        }
+        table.insert(resolved, value);
     }
 
     Ok(count)
 }
 
-fn resolve_value(value: &mut Value, root: &Table) -> Result<usize, ConfigError> {
+fn resolve_keys_in(
+    value: &mut Value,
+    resolver: &mut Resolver,
+    key_path: &mut Vec<String>,
+) -> Result<usize, ConfigError> {
     match value {
-        Value::String(s) => resolve_string(s, root),
-        Value::Table(t) => resolve_pass(t, root),
+        Value::Table(t) => resolve_keys(t, resolver, key_path),
         Value::Array(arr) => {
             let mut count = 0;
-            for item in arr.iter_mut() {
-                count += resolve_value(item, root)?;
+            for (index, item) in arr.iter_mut().enumerate() {
+                key_path.push(index.to_string());
+                count += resolve_keys_in(item, resolver, key_path)?;
+                key_path.pop();
             }
             Ok(count)
         }
@@ -40,36 +171,223 @@ fn resolve_value(value: &mut Value, root: &Table) -> Result<usize, ConfigError>
     }
 }
 
-fn resolve_string(s: &mut String, root: &Table) -> Result<usize, ConfigError> {
+fn unescape_table(
+    table: &mut Table,
+    delimiters: &Delimiters,
+    key_path: &mut Vec<String>,
+) -> Result<(), ConfigError> {
+    for (key, value) in table.iter_mut() {
+        key_path.push(key.clone());
+        unescape_value(value, delimiters, key_path)?;
+        key_path.pop();
+    }
+    Ok(())
+}
+
+fn unescape_value(
+    value: &mut Value,
+    delimiters: &Delimiters,
+    key_path: &mut Vec<String>,
+) -> Result<(), ConfigError> {
+    match value {
+        Value::String(s) => *s = unescape(s, delimiters, key_path)?,
+        Value::Table(t) => unescape_table(t, delimiters, key_path)?,
+        Value::Array(arr) => {
+            for (index, item) in arr.iter_mut().enumerate() {
+                key_path.push(index.to_string());
+                unescape_value(item, delimiters, key_path)?;
+                key_path.pop();
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Also the final sweep: any unescaped open marker left here was never resolved
+fn unescape(s: &str, delimiters: &Delimiters, key_path: &[String]) -> Result<String, ConfigError> {
+    let escape = delimiters.escape();
     let mut result = String::with_capacity(s.len());
-    let mut substitutions = 0;
-    let mut chars = s.chars().peekable();
-
-    while let Some(ch) = chars.next() {
-        if ch == '$' {
-            match chars.peek() {
-                Some('$') => {
-                    // Escape sequence: $$ -> $
-                    chars.next();
-                    result.push('$');
-                }
-                Some('{') => {
-                    // Reference: ${path.to.field}
-                    chars.next(); // consume '{'
-                    let path = consume_until(&mut chars, '}')
-                        .ok_or(ConfigError::UnclosedReference)?;
-
-                    let resolved = lookup_path(root, &path)?;
-                    result.push_str(&resolved);
-                    substitutions += 1;
+    let mut rest = s;
+
+    while let Some(ch) = rest.chars().next() {
+        if rest.starts_with(&delimiters.open) {
+            return Err(ConfigError::UnresolvedReference(key_path.join(".")));
+        }
+        rest = &rest[ch.len_utf8()..];
+        result.push(ch);
+        // The doubled escape char stands for one
+        if ch == escape && rest.starts_with(escape) {
+            rest = &rest[escape.len_utf8()..];
+        }
+    }
+
+    Ok(result)
+}
+
+fn attach_origin(error: ConfigError, key_path: &[String], provenance: &Provenance) -> ConfigError {
+    match error {
+        ConfigError::ReferenceNotFound { path, .. } => ConfigError::ReferenceNotFound {
+            path,
+            in_value_from: provenance.origin_of(key_path).map(|p| p.to_path_buf()),
+        },
+        other => other,
+    }
+}
+
+struct Resolver<'a> {
+    root: &'a Table,
+    mode: ReferenceMode,
+    // Bounds recursion so deeply nested untrusted input can't overflow the stack
+    max_depth: usize,
+    delimiters: &'a Delimiters,
+    // Keys were normalized as they merged, so reference paths must be too
+    key_case: Option<KeyCase>,
+    env: Option<&'a HashMap<String, String>>,
+    dangling: &'a mut Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn scalar(&mut self, path: &str) -> Result<String, ConfigError> {
+        match lookup_chain(self.root, path, self.key_case, self.env) {
+            Err(ConfigError::ReferenceNotFound { .. }) if self.mode == ReferenceMode::Lenient => {
+                // A copied reference can dangle again on a later pass
+                if !self.dangling.iter().any(|p| p == path) {
+                    self.dangling.push(path.to_string());
                 }
-                _ => {
-                    // Just a lone $
-                    result.push('$');
+                Ok(String::new())
+            }
+            other => other,
+        }
+    }
+}
+
+// On error, `key_path` is left pointing at the value that failed to resolve
+fn resolve_pass(
+    table: &mut Table,
+    resolver: &mut Resolver,
+    key_path: &mut Vec<String>,
+) -> Result<usize, ConfigError> {
+    let mut count = 0;
+
+    for (key, value) in table.iter_mut() {
+        key_path.push(key.clone());
+        count += resolve_value(value, resolver, key_path)?;
+        key_path.pop();
+    }
+
+    Ok(count)
+}
+
+fn resolve_value(
+    value: &mut Value,
+    resolver: &mut Resolver,
+    key_path: &mut Vec<String>,
+) -> Result<usize, ConfigError> {
+    match value {
+        Value::String(s) => {
+            // A string that is exactly one reference to a table or array is
+            // replaced by a copy of that value rather than stringified
+            if let Some(target) = structural_target(s, resolver) {
+                // Wait until the target is fully resolved before copying it;
+                // a cycle never settles and ends in CircularReference
+                if !has_references(&target, resolver.delimiters) {
+                    *value = target;
                 }
+                return Ok(1);
             }
+            resolve_string(s, resolver, key_path)
+        }
+        Value::Table(_) | Value::Array(_) if key_path.len() > resolver.max_depth => {
+            Err(ConfigError::MaxDepthExceeded(resolver.max_depth))
+        }
+        Value::Table(t) => resolve_pass(t, resolver, key_path),
+        Value::Array(arr) => {
+            let mut count = 0;
+            for (index, item) in arr.iter_mut().enumerate() {
+                key_path.push(index.to_string());
+                count += resolve_value(item, resolver, key_path)?;
+                key_path.pop();
+            }
+            Ok(count)
+        }
+        _ => Ok(0),
+    }
+}
+
+fn structural_target(s: &str, resolver: &Resolver) -> Option<Value> {
+    let delimiters = resolver.delimiters;
+    let path = s
+        .strip_prefix(delimiters.open.as_str())?
+        .strip_suffix(delimiters.close.as_str())?;
+    if path.contains(delimiters.close.as_str()) {
+        return None;
+    }
+
+    // Lookup errors are left for resolve_string to report
+    lookup_value(resolver.root, &normalize_path(path, resolver.key_case))
+        .ok()
+        .filter(|target| target.is_table() || target.is_array())
+        .cloned()
+}
+
+fn has_references(value: &Value, delimiters: &Delimiters) -> bool {
+    match value {
+        Value::String(s) => contains_reference(s, delimiters),
+        Value::Table(t) => t.values().any(|v| has_references(v, delimiters)),
+        Value::Array(arr) => arr.iter().any(|v| has_references(v, delimiters)),
+        _ => false,
+    }
+}
+
+fn contains_reference(s: &str, delimiters: &Delimiters) -> bool {
+    let escape = delimiters.escape();
+    let mut rest = s;
+    while let Some(ch) = rest.chars().next() {
+        if rest.starts_with(&delimiters.open) {
+            return true;
+        }
+        rest = &rest[ch.len_utf8()..];
+        // Skipping the second escape char keeps `$${` from matching
+        if ch == escape && rest.starts_with(escape) {
+            rest = &rest[escape.len_utf8()..];
+        }
+    }
+    false
+}
+
+fn resolve_string(
+    s: &mut String,
+    resolver: &mut Resolver,
+    key_path: &[String],
+) -> Result<usize, ConfigError> {
+    let Delimiters { open, close } = resolver.delimiters;
+    let escape = resolver.delimiters.escape();
+    let mut result = String::with_capacity(s.len());
+    let mut substitutions = 0;
+    let mut rest = s.as_str();
+
+    while let Some(ch) = rest.chars().next() {
+        if ch == escape && rest[ch.len_utf8()..].starts_with(escape) {
+            // Escape sequence: kept as-is until every pass is done
+            result.push(escape);
+            result.push(escape);
+            rest = &rest[2 * escape.len_utf8()..];
+        } else if let Some(body) = rest.strip_prefix(open.as_str()) {
+            // Reference: ${path.to.field}
+            let (path, after) =
+                body.split_once(close.as_str())
+                    .ok_or_else(|| ConfigError::UnclosedReference {
+                        path: key_path.join("."),
+                        context: unclosed_context(rest),
+                    })?;
+            let resolved = resolver.scalar(path)?;
+            result.push_str(&resolved);
+            substitutions += 1;
+            rest = after;
         } else {
             result.push(ch);
+            rest = &rest[ch.len_utf8()..];
         }
     }
 
@@ -77,37 +395,90 @@ fn resolve_string(s: &mut String, root: &Table) -> Result<usize, ConfigError> {
     Ok(substitutions)
 }
 
-fn consume_until(chars: &mut std::iter::Peekable<std::str::Chars>, delim: char) -> Option<String> {
-    let mut result = String::new();
-    for ch in chars.by_ref() {
-        if ch == delim {
-            return Some(result);
+// The unclosed reference from its opening marker, shortened with `...` if long
+fn unclosed_context(rest: &str) -> String {
+    let mut context: String = rest.chars().take(CONTEXT_LEN).collect();
+    if rest.chars().nth(CONTEXT_LEN).is_some() {
+        context.push_str("...");
+    }
+    context
+}
+
+// `${db.pass|env:DB_PASS|-changeme}`: alternatives are tried in order until
+// one resolves. A `-` default always does, so everything after it (pipes
+// included) is literal; it isn't recognized first, where `-` may start a key
+fn lookup_chain(
+    root: &Table,
+    reference: &str,
+    key_case: Option<KeyCase>,
+    env: Option<&HashMap<String, String>>,
+) -> Result<String, ConfigError> {
+    let mut rest = reference;
+    let mut first = true;
+
+    loop {
+        if let (false, Some(default)) = (first, rest.strip_prefix('-')) {
+            return Ok(default.to_string());
         }
-        result.push(ch);
+        let (alternative, next) = match rest.split_once('|') {
+            Some((alternative, next)) => (alternative, Some(next)),
+            None => (rest, None),
+        };
+        let resolved = match alternative.strip_prefix("env:") {
+            Some(name) => match env {
+                Some(env) => env.get(name).cloned(),
+                None => std::env::var(name).ok(),
+            },
+            // `env:` names and `-` defaults are taken as written
+            None => match lookup_path(root, &normalize_path(alternative, key_case)) {
+                Err(ConfigError::ReferenceNotFound { .. }) => None,
+                other => Some(other?),
+            },
+        };
+
+        match (resolved, next) {
+            (Some(value), _) => return Ok(value),
+            (None, Some(next)) => rest = next,
+            (None, None) => {
+                return Err(ConfigError::ReferenceNotFound {
+                    path: reference.to_string(),
+                    in_value_from: None,
+                })
+            }
+        }
+        first = false;
     }
-    None // Delimiter not found
 }
 
 fn lookup_path(root: &Table, path: &str) -> Result<String, ConfigError> {
-    let parts: Vec<&str> = path.split('.').collect();
-    if parts.is_empty() || parts.iter().any(|p| p.is_empty()) {
+    value_to_string(lookup_value(root, path)?, path)
+}
+
+pub fn lookup_value<'a>(root: &'a Table, path: &str) -> Result<&'a Value, ConfigError> {
+    let parts = split_path(path);
+    if parts.iter().any(String::is_empty) {
         return Err(ConfigError::InvalidReferencePath(path.to_string()));
     }
 
-    let not_found = || ConfigError::ReferenceNotFound(path.to_string());
+    let not_found = || ConfigError::ReferenceNotFound {
+        path: path.to_string(),
+        in_value_from: None,
+    };
 
     // First lookup from root table
-    let mut current = root.get(parts[0]).ok_or_else(not_found)?;
+    let mut current = root.get(&parts[0]).ok_or_else(not_found)?;
 
-    // Traverse remaining path segments
+    // Traverse remaining path segments; numeric segments index into arrays
     for part in &parts[1..] {
-        current = current
-            .as_table()
-            .and_then(|t| t.get(*part))
-            .ok_or_else(not_found)?;
+        current = match current {
+            Value::Table(table) => table.get(part),
+            Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+        .ok_or_else(not_found)?;
     }
 
-    value_to_string(current, path)
+    Ok(current)
 }
 
 fn value_to_string(value: &Value, path: &str) -> Result<String, ConfigError> {
@@ -116,9 +487,17 @@ fn value_to_string(value: &Value, path: &str) -> Result<String, ConfigError> {
         Value::Integer(i) => Ok(i.to_string()),
         Value::Float(f) => Ok(f.to_string()),
         Value::Boolean(b) => Ok(b.to_string()),
-        Value::Datetime(dt) => Ok(dt.to_string()),
-        Value::Array(_) | Value::Table(_) => {
-            Err(ConfigError::NonScalarReference(path.to_string()))
-        }
+        Value::Datetime(dt) => Ok(datetime_to_string(dt)),
+        Value::Array(_) | Value::Table(_) => Err(ConfigError::NonScalarReference(path.to_string())),
+    }
+}
+
+fn datetime_to_string(dt: &Datetime) -> String {
+    match (&dt.date, &dt.time, &dt.offset) {
+        // Offset datetimes render as RFC 3339 (`T` separator, `Z` or `+hh:mm`)
+        (Some(date), Some(time), Some(offset)) => format!("{date}T{time}{offset}"),
+        // Local datetimes, dates and times keep their natural TOML form:
+        // `2020-01-01` and `12:30:00` are never widened to a full timestamp
+        _ => dt.to_string(),
     }
 }
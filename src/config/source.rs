@@ -1,8 +1,12 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
 use toml::{Table, Value};
 
+use super::env::EnvOptions;
 use super::ConfigError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConfigEntry {
     pub path: Vec<String>,
     pub value: Value,
@@ -19,56 +23,230 @@ impl ConfigEntry {
     pub fn at_path(path: Vec<String>, value: Value) -> Self {
         Self { path, value }
     }
+
+    pub fn leaf(path: &str, value: impl Into<Value>) -> Self {
+        Self::at_path(split_path(path), value.into())
+    }
 }
 
-pub trait ConfigSource: Send + Sync + std::fmt::Debug {
+pub trait ConfigSource: Send + Sync + std::fmt::Debug + CloneSource {
     fn entries(&self) -> Result<Vec<ConfigEntry>, ConfigError>;
+
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    fn describe(&self) -> String {
+        format!("{self:?}")
+    }
+
+    // Whether the source fails the build when its file is missing, as
+    // `FileSource` and `GlobSource` built with `required = true` do
+    fn is_required(&self) -> bool {
+        false
+    }
+
+    // Env-style sources return their options, so the builder can apply
+    // `strict` and `existing_only`, which need the config merged so far
+    fn env_options(&self) -> Option<&EnvOptions> {
+        None
+    }
+
+    // Sources without a file count as values set in code unless they say
+    // otherwise, e.g. a CLI arguments source returning `Origin::Cli`
+    fn origin(&self) -> Origin {
+        match self.path() {
+            Some(path) => Origin::File(path.to_path_buf()),
+            None => Origin::Override,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    File(PathBuf),
+    Env,
+    Cli,
+    Override,
+    Default,
 }
 
-pub fn merge_at_path(table: &mut Table, path: &[String], value: Value) {
-    if path.is_empty() {
-        // Root-level merge: deep merge if value is a table
-        if let Value::Table(overlay) = value {
-            deep_merge(table, overlay);
+impl Origin {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Origin::File(path) => Some(path),
+            _ => None,
         }
-        return;
     }
+}
 
-    // Non-empty path: navigate to target location
-    let (first, rest) = path.split_first().expect("path is non-empty");
+// Lets `Box<dyn ConfigSource>` be cloned; implemented for every `Clone` source
+pub trait CloneSource {
+    fn clone_box(&self) -> Box<dyn ConfigSource>;
+}
+
+impl<T: ConfigSource + Clone + 'static> CloneSource for T {
+    fn clone_box(&self) -> Box<dyn ConfigSource> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn ConfigSource> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Provenance {
+    origins: BTreeMap<Vec<String>, Origin>,
+}
 
-    if rest.is_empty() {
-        // At final key: merge or replace depending on types
-        match (table.get_mut(first), &value) {
-            (Some(Value::Table(base)), Value::Table(overlay)) => {
-                deep_merge(base, overlay.clone());
+impl Provenance {
+    pub fn record(&mut self, path: &[String], value: &Value, origin: &Origin) {
+        for len in 1..path.len() {
+            self.origins.remove(&path[..len]);
+        }
+
+        match value {
+            Value::Table(table) => {
+                self.origins.remove(path);
+                for (key, value) in table {
+                    let mut child = path.to_vec();
+                    child.push(key.clone());
+                    self.record(&child, value, origin);
+                }
             }
+            // Non-table root entries are rejected by merge_at_path
+            _ if path.is_empty() => {}
             _ => {
-                table.insert(first.clone(), value);
+                self.origins.retain(|p, _| !p.starts_with(path));
+                self.origins.insert(path.to_vec(), origin.clone());
             }
         }
-        return;
     }
 
-    // More path segments remain: ensure intermediate table exists
-    if !matches!(table.get(first), Some(Value::Table(_))) {
-        table.insert(first.clone(), Value::Table(Table::new()));
+    // File-set keys that an entry at `path` would replace: any recorded key
+    // that is an ancestor or descendant of one of the entry's leaves
+    pub fn overridden_by(&self, path: &[String], value: &Value) -> Vec<(Vec<String>, &Path)> {
+        let mut leaves = Vec::new();
+        collect_leaves(path.to_vec(), value, &mut leaves);
+
+        self.origins
+            .iter()
+            .filter_map(|(key, origin)| {
+                let origin = origin.path()?;
+                leaves
+                    .iter()
+                    .any(|leaf| leaf.starts_with(key) || key.starts_with(leaf))
+                    .then(|| (key.clone(), origin))
+            })
+            .collect()
     }
 
-    if let Some(Value::Table(nested)) = table.get_mut(first) {
-        merge_at_path(nested, rest, value);
+    pub fn origin_of(&self, path: &[String]) -> Option<&Path> {
+        (1..=path.len())
+            .rev()
+            .find_map(|len| self.origins.get(&path[..len]))
+            .and_then(Origin::path)
+    }
+
+    // Keyed like `keys`, except that arrays count as leaves; entries for keys
+    // since removed (by the unset sentinel or a later table) are left out
+    pub fn origins(&self, table: &Table) -> HashMap<String, Origin> {
+        self.origins
+            .iter()
+            .filter(|(path, _)| path_exists(table, path))
+            .map(|(path, origin)| {
+                let segments: Vec<String> = path.iter().map(|s| escape_segment(s)).collect();
+                (segments.join("."), origin.clone())
+            })
+            .collect()
     }
 }
 
-fn deep_merge(base: &mut Table, overlay: Table) {
-    for (key, value) in overlay {
-        match (base.get_mut(&key), value) {
-            (Some(Value::Table(base_table)), Value::Table(overlay_table)) => {
-                deep_merge(base_table, overlay_table);
+fn collect_leaves(path: Vec<String>, value: &Value, leaves: &mut Vec<Vec<String>>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let mut child = path.clone();
+                child.push(key.clone());
+                collect_leaves(child, value, leaves);
             }
-            (_, value) => {
-                base.insert(key, value);
+        }
+        _ if path.is_empty() => {}
+        _ => leaves.push(path),
+    }
+}
+
+// Splits a dotted path into segments; `\.` keeps a literal dot inside a
+// segment and `\\` a literal backslash
+pub fn split_path(path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped @ ('.' | '\\')) => current.push(escaped),
+                Some(other) => {
+                    current.push('\\');
+                    current.push(other);
+                }
+                None => current.push('\\'),
+            },
+            '.' => segments.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+
+    segments.push(current);
+    segments
+}
+
+// Dotted path of every scalar leaf, array elements by index, in sorted key
+// order; segments are escaped so each path round-trips through `split_path`
+pub fn keys(table: &Table) -> Vec<String> {
+    let mut keys = Vec::new();
+    for (key, value) in table {
+        collect_keys(value, escape_segment(key), &mut keys);
+    }
+    keys
+}
+
+fn collect_keys(value: &Value, path: String, keys: &mut Vec<String>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                collect_keys(value, format!("{path}.{}", escape_segment(key)), keys);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_keys(item, format!("{path}.{index}"), keys);
             }
         }
+        _ => keys.push(path),
+    }
+}
+
+pub(super) fn escape_segment(key: &str) -> String {
+    key.replace('\\', "\\\\").replace('.', "\\.")
+}
+
+// Numeric segments index into arrays, as in `${servers.0.port}`
+pub fn path_exists(table: &Table, path: &[String]) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return true;
+    };
+    let mut current = table.get(first);
+    for key in rest {
+        current = match current {
+            Some(Value::Table(nested)) => nested.get(key),
+            Some(Value::Array(items)) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        };
     }
+    current.is_some()
 }
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+// Build diagnostics for the `tracing` feature. Every function here compiles
+// to nothing without it, so call sites don't need their own `cfg`s
+
+pub(super) struct BuildSpan {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+pub(super) fn enter_build(sources: usize) -> BuildSpan {
+    #[cfg(not(feature = "tracing"))]
+    let _ = sources;
+    BuildSpan {
+        #[cfg(feature = "tracing")]
+        _span: tracing::debug_span!("dragon_fnd::build", sources).entered(),
+    }
+}
+
+pub(super) fn file_loaded(path: &Path) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(path = %path.display(), "loaded file");
+    #[cfg(not(feature = "tracing"))]
+    let _ = path;
+}
+
+pub(super) fn file_skipped(path: &Path) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(path = %path.display(), "skipped missing optional file");
+    #[cfg(not(feature = "tracing"))]
+    let _ = path;
+}
+
+pub(super) fn env_matched(source: &str, count: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(source, count, "matched env vars");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (source, count);
+}
+
+pub(super) fn references_resolved(count: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(count, "resolved references");
+    #[cfg(not(feature = "tracing"))]
+    let _ = count;
+}
+
+pub(super) fn deserializing<T>() {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target_type = std::any::type_name::<T>(), "deserializing");
+}
@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use arc_swap::ArcSwap;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use serde::de::DeserializeOwned;
+
+use super::builder::Preloaded;
+use super::source::{ConfigEntry, ConfigSource};
+use super::{Config, ConfigError};
+
+#[derive(Debug)]
+pub struct WatchedConfig<T> {
+    state: Arc<State<T>>,
+    errors: Receiver<ConfigError>,
+}
+
+impl<T> WatchedConfig<T> {
+    pub fn load(&self) -> Arc<T> {
+        self.state.current.load_full()
+    }
+
+    pub fn errors(&self) -> &Receiver<ConfigError> {
+        &self.errors
+    }
+}
+
+impl<T: DeserializeOwned> WatchedConfig<T> {
+    pub fn reload_source(&self, index: usize) -> Result<(), ConfigError> {
+        let files = self
+            .state
+            .config
+            .file_sources_at(index)
+            .ok_or(ConfigError::NotAFileSource(index))?;
+        self.state.reload(&files)
+    }
+}
+
+// Shared with the watcher thread
+#[derive(Debug)]
+struct State<T> {
+    config: Config,
+    current: ArcSwap<T>,
+    // Parsed entries of every file, so a reload only reads the files it names
+    cache: Mutex<HashMap<PathBuf, Vec<ConfigEntry>>>,
+}
+
+impl<T: DeserializeOwned> State<T> {
+    // Keeps serving the last good config if the rebuild fails
+    fn reload(&self, files: &[Box<dyn ConfigSource>]) -> Result<(), ConfigError> {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        refresh(&self.config, &mut cache, files);
+        self.current
+            .store(Arc::new(self.config.load_preloaded(preloaded(&cache))?));
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Watcher {
+    _watcher: RecommendedWatcher,
+}
+
+pub(super) fn watch<T>(config: Config) -> Result<(WatchedConfig<T>, Watcher), ConfigError>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    let mut cache = HashMap::new();
+    refresh(&config, &mut cache, &config.file_sources());
+    let current = ArcSwap::from_pointee(config.load_preloaded::<T>(preloaded(&cache))?);
+    let (error_tx, errors) = mpsc::channel();
+
+    // Watch parent directories rather than the files themselves, so files that
+    // are replaced on save (as most editors do) or created later are picked up
+    let files: HashSet<PathBuf> = config.file_paths().iter().map(|p| watch_key(p)).collect();
+    let dirs: HashSet<PathBuf> = files
+        .iter()
+        .filter_map(|f| f.parent())
+        .filter(|d| d.is_dir())
+        .map(Path::to_path_buf)
+        .collect();
+
+    let state = Arc::new(State {
+        config,
+        current,
+        cache: Mutex::new(cache),
+    });
+    let target = Arc::clone(&state);
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                let _ = error_tx.send(e.into());
+                return;
+            }
+        };
+
+        if matches!(event.kind, EventKind::Access(_))
+            || !event.paths.iter().any(|p| files.contains(&watch_key(p)))
+        {
+            return;
+        }
+
+        // Every file is read again, as the change may have added glob matches
+        if let Err(e) = target.reload(&target.config.file_sources()) {
+            let _ = error_tx.send(e);
+        }
+    })?;
+
+    for dir in &dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok((
+        WatchedConfig { state, errors },
+        Watcher { _watcher: watcher },
+    ))
+}
+
+// A file that fails to read is dropped from the cache, so the merge reads it
+// again and reports the error
+fn refresh(
+    config: &Config,
+    cache: &mut HashMap<PathBuf, Vec<ConfigEntry>>,
+    files: &[Box<dyn ConfigSource>],
+) {
+    for (path, entries) in config.read_files(files) {
+        match entries {
+            Ok(entries) => cache.insert(path, entries),
+            Err(_) => cache.remove(&path),
+        };
+    }
+}
+
+fn preloaded(cache: &HashMap<PathBuf, Vec<ConfigEntry>>) -> Preloaded {
+    cache
+        .iter()
+        .map(|(path, entries)| (path.clone(), Ok(entries.clone())))
+        .collect()
+}
+
+fn watch_key(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match (absolute.parent(), absolute.file_name()) {
+        (Some(dir), Some(name)) => dir
+            .canonicalize()
+            .unwrap_or_else(|_| dir.to_path_buf())
+            .join(name),
+        _ => absolute,
+    }
+}
@@ -0,0 +1,41 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Mutex;
+
+pub(super) type Hook = Box<dyn FnOnce() + Send>;
+
+// Shared by every clone of a context, so the hooks run once, when the last
+// clone is dropped
+pub(super) struct ShutdownHooks {
+    hooks: Mutex<Vec<Hook>>,
+}
+
+impl ShutdownHooks {
+    pub(super) fn new(hooks: Vec<Hook>) -> Self {
+        Self {
+            hooks: Mutex::new(hooks),
+        }
+    }
+}
+
+impl Drop for ShutdownHooks {
+    fn drop(&mut self) {
+        let hooks = std::mem::take(self.hooks.get_mut().unwrap_or_else(|e| e.into_inner()));
+        // Run in reverse registration order; a panicking hook doesn't stop the rest
+        for hook in hooks.into_iter().rev() {
+            let _ = catch_unwind(AssertUnwindSafe(hook));
+        }
+    }
+}
+
+impl std::fmt::Debug for ShutdownHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.hooks.lock().map_or(0, |hooks| hooks.len());
+        write!(f, "<{count} shutdown hooks>")
+    }
+}
+
+pub(super) fn run_build_hooks(hooks: Vec<Hook>) {
+    for hook in hooks {
+        hook();
+    }
+}
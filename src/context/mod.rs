@@ -1,40 +1,219 @@
-use crate::Error;
+mod lifecycle;
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::config::lookup_value;
+use crate::{Config, Error};
+
+use lifecycle::{run_build_hooks, Hook, ShutdownHooks};
+
+// Values keyed by `TypeId::of` their own type
+pub type TypeMap = HashMap<TypeId, Box<dyn Any + Send + Sync>>;
 
 #[derive(Debug)]
 pub struct AppContext<C> {
-    config: C,
+    config: Arc<C>,
+    configs: Arc<TypeMap>,
+    resources: Arc<TypeMap>,
+    shutdown: Arc<ShutdownHooks>,
+}
+
+// What `into_parts` takes out of a context
+#[derive(Debug)]
+pub struct ContextParts<C> {
+    pub config: C,
+    pub resources: TypeMap,
+    // Registered with `with_config_as`
+    pub sections: TypeMap,
+}
+
+// Manual impl: cloning only bumps reference counts, so `C: Clone` isn't needed
+impl<C> Clone for AppContext<C> {
+    fn clone(&self) -> Self {
+        Self {
+            config: Arc::clone(&self.config),
+            configs: Arc::clone(&self.configs),
+            resources: Arc::clone(&self.resources),
+            shutdown: Arc::clone(&self.shutdown),
+        }
+    }
 }
 
 impl<C> AppContext<C> {
     pub fn config(&self) -> &C {
         &self.config
     }
+
+    pub fn config_arc(&self) -> Arc<C> {
+        Arc::clone(&self.config)
+    }
+
+    // Like `Arc::try_unwrap`: hands the context back while other clones or
+    // `config_arc` handles still share the config
+    pub fn into_config(self) -> Result<C, Self> {
+        Arc::try_unwrap(self.config).map_err(|config| Self { config, ..self })
+    }
+
+    // Like `into_config`, keeping what was registered beside the config
+    pub fn into_parts(self) -> Result<ContextParts<C>, Self> {
+        let config = match Arc::try_unwrap(self.config) {
+            Ok(config) => config,
+            Err(config) => return Err(Self { config, ..self }),
+        };
+        // Only contexts hold the maps, and each holds the config too, so an
+        // unshared config means no other context is left
+        Ok(ContextParts {
+            config,
+            resources: Arc::try_unwrap(self.resources).expect("no other context is left"),
+            sections: Arc::try_unwrap(self.configs).expect("no other context is left"),
+        })
+    }
+
+    pub fn resource<R: Send + Sync + 'static>(&self) -> Option<&R> {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .and_then(|r| r.downcast_ref::<R>())
+    }
+}
+
+impl<C: DeserializeOwned + 'static> AppContext<C> {
+    pub fn from_config(config: Config) -> Result<Self, Error> {
+        AppContext::builder().with_config(config.build()?).build()
+    }
+}
+
+impl<C: 'static> AppContext<C> {
+    pub fn config_as<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        (self.config.as_ref() as &dyn Any)
+            .downcast_ref::<T>()
+            .or_else(|| {
+                self.configs
+                    .get(&TypeId::of::<T>())
+                    .and_then(|c| c.downcast_ref::<T>())
+            })
+    }
+}
+
+impl<C: Serialize> AppContext<C> {
+    pub fn try_path<T: DeserializeOwned>(&self, path: &str) -> Option<T> {
+        let toml::Value::Table(root) = toml::Value::try_from(self.config.as_ref()).ok()? else {
+            return None;
+        };
+        lookup_value(&root, path).ok()?.clone().try_into().ok()
+    }
 }
 
 impl AppContext<()> {
-    pub fn builder() -> AppContextBuilder<()> {
-        AppContextBuilder { config: None }
+    pub fn builder() -> AppContextBuilder<(), NoConfig> {
+        AppContextBuilder {
+            config: (),
+            configs: TypeMap::new(),
+            resources: TypeMap::new(),
+            duplicate: None,
+            on_build: Vec::new(),
+            on_shutdown: Vec::new(),
+            state: PhantomData,
+        }
     }
 }
 
+// Builder states: `build` only exists once `with_config` has moved the
+// builder from `NoConfig` to `HasConfig`
 #[derive(Debug)]
+pub struct NoConfig;
+
+#[derive(Debug)]
+pub struct HasConfig;
+
 #[must_use = "builders do nothing until .build() is called"]
-pub struct AppContextBuilder<C> {
-    config: Option<C>,
+pub struct AppContextBuilder<C, S = HasConfig> {
+    config: C,
+    configs: TypeMap,
+    resources: TypeMap,
+    // First config type registered twice, reported by `build`
+    duplicate: Option<&'static str>,
+    on_build: Vec<Hook>,
+    on_shutdown: Vec<Hook>,
+    state: PhantomData<S>,
+}
+
+impl<C: std::fmt::Debug, S> std::fmt::Debug for AppContextBuilder<C, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AppContextBuilder")
+            .field("config", &self.config)
+            .field("configs", &self.configs)
+            .field("resources", &self.resources)
+            .field("duplicate", &self.duplicate)
+            .field("on_build", &self.on_build.len())
+            .field("on_shutdown", &self.on_shutdown.len())
+            .finish()
+    }
 }
 
-impl AppContextBuilder<()> {
+impl AppContextBuilder<(), NoConfig> {
     pub fn with_config<C>(self, config: C) -> AppContextBuilder<C> {
         AppContextBuilder {
-            config: Some(config),
+            config,
+            configs: self.configs,
+            resources: self.resources,
+            duplicate: self.duplicate,
+            on_build: self.on_build,
+            on_shutdown: self.on_shutdown,
+            state: PhantomData,
         }
     }
 }
 
-impl<C> AppContextBuilder<C> {
+impl<C, S> AppContextBuilder<C, S> {
+    pub fn with_config_as<T: Send + Sync + 'static>(mut self, config: T) -> Self {
+        let previous = self.configs.insert(TypeId::of::<T>(), Box::new(config));
+        if previous.is_some() {
+            self.duplicate.get_or_insert(std::any::type_name::<T>());
+        }
+        self
+    }
+
+    pub fn with_resource<R: Send + Sync + 'static>(mut self, resource: R) -> Self {
+        self.resources.insert(TypeId::of::<R>(), Box::new(resource));
+        self
+    }
+
+    pub fn on_build(mut self, hook: impl FnOnce() + Send + 'static) -> Self {
+        self.on_build.push(Box::new(hook));
+        self
+    }
+
+    pub fn on_shutdown(mut self, hook: impl FnOnce() + Send + 'static) -> Self {
+        self.on_shutdown.push(Box::new(hook));
+        self
+    }
+}
+
+impl<C: 'static> AppContextBuilder<C> {
     pub fn build(self) -> Result<AppContext<C>, Error> {
-        Ok(AppContext {
-            config: self.config.ok_or(Error::MissingConfig)?,
-        })
+        // `config_as` finds the primary config first, so a section of the same
+        // type would be unreachable
+        let shadowed = || {
+            self.configs
+                .contains_key(&TypeId::of::<C>())
+                .then(std::any::type_name::<C>)
+        };
+        if let Some(type_name) = self.duplicate.or_else(shadowed) {
+            return Err(Error::DuplicateConfig(type_name));
+        }
+        let context = AppContext {
+            config: Arc::new(self.config),
+            configs: Arc::new(self.configs),
+            resources: Arc::new(self.resources),
+            shutdown: Arc::new(ShutdownHooks::new(self.on_shutdown)),
+        };
+        run_build_hooks(self.on_build);
+        Ok(context)
     }
 }
@@ -7,6 +7,6 @@ pub enum Error {
     #[error("configuration error: {0}")]
     Config(#[from] ConfigError),
 
-    #[error("application context requires a configuration")]
-    MissingConfig,
+    #[error("configuration of type `{0}` registered more than once")]
+    DuplicateConfig(&'static str),
 }
@@ -1,7 +1,11 @@
 pub mod config;
 pub mod context;
 mod error;
+pub mod maybe;
+pub mod secret;
 
 pub use config::{Config, ConfigError};
 pub use context::AppContext;
 pub use error::Error;
+pub use maybe::Maybe;
+pub use secret::Secret;
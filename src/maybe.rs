@@ -0,0 +1,65 @@
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Visitor};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Maybe<T> {
+    #[default]
+    Absent,
+    Present(T),
+}
+
+impl<T> Maybe<T> {
+    pub fn is_present(&self) -> bool {
+        matches!(self, Maybe::Present(_))
+    }
+
+    pub fn is_absent(&self) -> bool {
+        matches!(self, Maybe::Absent)
+    }
+
+    pub fn as_option(&self) -> Option<&T> {
+        match self {
+            Maybe::Present(value) => Some(value),
+            Maybe::Absent => None,
+        }
+    }
+
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Maybe::Present(value) => Some(value),
+            Maybe::Absent => None,
+        }
+    }
+}
+
+// Goes through `deserialize_option` like `Option<T>` does: serde's derive
+// hands a missing field to that as `none`, so no `#[serde(default)]` is needed.
+// TOML has no null, so a key that is present always arrives as `some`
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Maybe<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_option(MaybeVisitor(PhantomData))
+    }
+}
+
+struct MaybeVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for MaybeVisitor<T> {
+    type Value = Maybe<T>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("an optional value")
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Maybe::Absent)
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Maybe::Absent)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        T::deserialize(deserializer).map(Maybe::Present)
+    }
+}
@@ -0,0 +1,42 @@
+use dragon_fnd::context::AppContext;
+
+#[derive(Debug, PartialEq)]
+struct Pool(u32);
+
+#[derive(Debug, PartialEq)]
+struct Limits(u32);
+
+#[test]
+fn test_into_parts_keeps_resources_and_sections() {
+    let context = AppContext::builder()
+        .with_config("app".to_string())
+        .with_resource(Pool(4))
+        .with_config_as(Limits(10))
+        .build()
+        .unwrap();
+
+    let mut parts = context.into_parts().unwrap();
+    assert_eq!(parts.config, "app");
+    let pool = parts.resources.remove(&std::any::TypeId::of::<Pool>());
+    assert_eq!(pool.unwrap().downcast::<Pool>().unwrap(), Box::new(Pool(4)));
+    let limits = parts.sections.remove(&std::any::TypeId::of::<Limits>());
+    assert_eq!(
+        limits.unwrap().downcast::<Limits>().unwrap(),
+        Box::new(Limits(10))
+    );
+}
+
+#[test]
+fn test_into_parts_hands_back_a_shared_context() {
+    let context = AppContext::builder()
+        .with_config("app".to_string())
+        .with_resource(Pool(4))
+        .build()
+        .unwrap();
+    let config = context.config_arc();
+
+    let context = context.into_parts().unwrap_err();
+    assert_eq!(context.resource::<Pool>(), Some(&Pool(4)));
+    drop(config);
+    assert_eq!(context.into_parts().unwrap().config, "app");
+}
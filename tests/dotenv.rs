@@ -0,0 +1,41 @@
+use dragon_fnd::config::{Config, ConfigError};
+
+fn write_dotenv(name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("dragon-fnd-{name}-{}.env", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_quoted_values_end_at_their_first_unescaped_quote() {
+    let path = write_dotenv(
+        "dotenv-quotes",
+        concat!(
+            "APP__A=\"x\" # the \"a\" value\n",
+            "APP__B='y' # it's 'b'\n",
+            "APP__C=\"say \\\"hi\\\"\" # \"c\"\n",
+        ),
+    );
+    let config: toml::Table = Config::builder()
+        .with_dotenv(&path, "APP", "__", true)
+        .build()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let expected: toml::Table = toml::from_str("a = \"x\"\nb = \"y\"\nc = 'say \"hi\"'").unwrap();
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_text_after_a_closing_quote_is_rejected() {
+    let path = write_dotenv("dotenv-trailing", "APP__A=ok\nAPP__B=\"x\" y\n");
+    let result: Result<toml::Table, _> = Config::builder()
+        .with_dotenv(&path, "APP", "__", true)
+        .build();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        result,
+        Err(ConfigError::InvalidDotenv { line: 2, .. })
+    ));
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "json")]
+
+use dragon_fnd::config::{Config, ConfigError};
+
+fn with_features(json: &str) -> Config {
+    let base: toml::Table = toml::from_str("[features]\na = true").unwrap();
+    let vars = [("APP__FEATURES".to_string(), json.to_string())];
+    Config::from_table(base)
+        .with_env_vars("APP", "__", vars)
+        .with_env_json(true)
+}
+
+#[test]
+fn test_existing_only_drops_new_keys_inside_json_values() {
+    let merged: toml::Table = with_features(r#"{"a": false, "evil": {"x": 1}}"#)
+        .with_env_existing_only(true)
+        .build()
+        .unwrap();
+    let expected: toml::Table = toml::from_str("[features]\na = false").unwrap();
+    assert_eq!(merged, expected);
+}
+
+#[test]
+fn test_strict_rejects_type_conflicts_inside_json_values() {
+    let result: Result<toml::Table, _> = with_features(r#"{"a": {"x": 1}}"#)
+        .with_env_strict(true)
+        .build();
+    assert!(matches!(
+        result,
+        Err(ConfigError::TypeConflict { path, existing: "boolean", incoming: "table" })
+            if path == "features.a"
+    ));
+}
+
+#[test]
+fn test_report_lists_integer_overflows_inside_json_values() {
+    let vars = [(
+        "APP__LIMITS".to_string(),
+        r#"{"max": 18446744073709551615, "list": [1, 18446744073709551615]}"#.to_string(),
+    )];
+    let (merged, report) = Config::builder()
+        .with_env_vars("APP", "__", vars)
+        .with_env_json(true)
+        .build_with_report::<toml::Table>()
+        .unwrap();
+    assert_eq!(report.integer_overflows(), ["limits.list.1", "limits.max"]);
+    assert_eq!(
+        merged["limits"]["max"],
+        toml::Value::String("18446744073709551615".to_string())
+    );
+}
+
+#[test]
+fn test_strict_rejects_integer_overflows_inside_json_values() {
+    let vars = [(
+        "APP__N".to_string(),
+        r#"{"x": 99999999999999999999}"#.to_string(),
+    )];
+    let result: Result<toml::Table, _> = Config::builder()
+        .with_env_vars("APP", "__", vars)
+        .with_env_json(true)
+        .with_env_strict(true)
+        .build();
+    assert!(matches!(
+        result,
+        Err(ConfigError::IntegerOverflow { path, value })
+            if path == "n.x" && value == "99999999999999999999"
+    ));
+}
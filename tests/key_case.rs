@@ -0,0 +1,105 @@
+use dragon_fnd::config::{ArrayMerge, Config, ConfigError, KeyCase};
+
+fn table(toml: &str) -> toml::Table {
+    toml::from_str(toml).unwrap()
+}
+
+fn env(name: &str, value: &str) -> [(String, String); 1] {
+    [(name.to_string(), value.to_string())]
+}
+
+#[test]
+fn test_existing_only_matches_normalized_env_paths() {
+    let config: toml::Table = Config::from_table(table("max-conn = 1"))
+        .with_env_vars("APP", "__", env("APP__MAX_CONN", "9"))
+        .with_key_normalization(KeyCase::KebabCase)
+        .with_env_existing_only(true)
+        .build()
+        .unwrap();
+    assert_eq!(config, table("max-conn = 9"));
+}
+
+#[test]
+fn test_env_allowlist_matches_normalized_paths() {
+    let config: toml::Table = Config::builder()
+        .with_env_vars("APP", "__", env("APP__MAX_CONN", "9"))
+        .with_env_allowlist(&["max-conn"])
+        .with_key_normalization(KeyCase::KebabCase)
+        .build()
+        .unwrap();
+    assert_eq!(config, table("max-conn = 9"));
+}
+
+#[test]
+fn test_env_denylist_matches_normalized_paths() {
+    let config: toml::Table = Config::from_table(table("max-conn = 1"))
+        .with_env_vars("APP", "__", env("APP__MAX_CONN", "9"))
+        .with_env_denylist(&["max-conn"])
+        .with_key_normalization(KeyCase::KebabCase)
+        .build()
+        .unwrap();
+    assert_eq!(config, table("max-conn = 1"));
+}
+
+#[test]
+fn test_require_keys_named_before_normalization() {
+    let config: toml::Table = Config::from_table(table("max-conn = 1"))
+        .require_keys(&["max-conn"])
+        .with_key_normalization(KeyCase::SnakeCase)
+        .build()
+        .unwrap();
+    assert_eq!(config, table("max_conn = 1"));
+}
+
+#[test]
+fn test_require_non_empty_named_after_normalization() {
+    let result: Result<toml::Table, _> = Config::from_table(table("my_name = \" \""))
+        .with_key_normalization(KeyCase::SnakeCase)
+        .require_non_empty(&["my-name"])
+        .build();
+    assert!(matches!(
+        result,
+        Err(ConfigError::EmptyRequiredValue(key)) if key == "my_name"
+    ));
+}
+
+#[test]
+fn test_path_array_merge_uses_normalized_path() {
+    let config: toml::Table = Config::from_table(table("my_list = [2]"))
+        .with_default("my_list", vec![1])
+        .with_path_array_merge("my-list", ArrayMerge::Append)
+        .with_key_normalization(KeyCase::SnakeCase)
+        .build()
+        .unwrap();
+    assert_eq!(config, table("my_list = [1, 2]"));
+}
+
+#[test]
+fn test_array_key_uses_normalized_path_and_key() {
+    let base = table(concat!(
+        "[[my-servers]]\nhost-name = \"a\"\nport = 1\n",
+        "[[my-servers]]\nhost-name = \"b\"\nport = 1",
+    ));
+    let overlay = table("[[my_servers]]\nhost_name = \"a\"\nport = 2");
+    let config: toml::Table = Config::from_table(overlay)
+        .with_defaults(&base)
+        .unwrap()
+        .with_key_normalization(KeyCase::SnakeCase)
+        .with_array_key("my-servers", "host-name")
+        .build()
+        .unwrap();
+    let expected = table(concat!(
+        "[[my_servers]]\nhost_name = \"a\"\nport = 2\n",
+        "[[my_servers]]\nhost_name = \"b\"\nport = 1",
+    ));
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn test_build_section_uses_normalized_path() {
+    let port: toml::Table = Config::from_table(table("[my_db]\nport = 5432"))
+        .with_key_normalization(KeyCase::KebabCase)
+        .build_section("my_db")
+        .unwrap();
+    assert_eq!(port, table("port = 5432"));
+}
@@ -0,0 +1,31 @@
+use dragon_fnd::config::{Config, ConfigError, ConfigSource, FileSource};
+
+#[test]
+fn test_restrict_new_sections_base_covers_every_required_source() {
+    let dir = std::env::temp_dir().join(format!("dragon-fnd-sections-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("base1.toml"), "[s1]\nx = 1").unwrap();
+    std::fs::write(dir.join("base2.toml"), "[s2]\nx = 1").unwrap();
+    std::fs::write(dir.join("known.toml"), "[s2]\nx = 2").unwrap();
+    std::fs::write(dir.join("new.toml"), "[s3]\nx = 2").unwrap();
+
+    let build = |overlay: &str| {
+        let sources: Vec<Box<dyn ConfigSource>> = vec![
+            Box::new(FileSource::new(dir.join("base1.toml"), true)),
+            Box::new(FileSource::new(dir.join("base2.toml"), true)),
+            Box::new(FileSource::new(dir.join(overlay), false)),
+        ];
+        Config::from_sources(sources)
+            .restrict_new_sections(true)
+            .build::<toml::Table>()
+    };
+
+    let merged = build("known.toml").unwrap();
+    let expected: toml::Table = toml::from_str("[s1]\nx = 1\n[s2]\nx = 2").unwrap();
+    assert_eq!(merged, expected);
+    assert!(matches!(
+        build("new.toml"),
+        Err(ConfigError::UnknownSection(section)) if section == "s3"
+    ));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "watch")]
+
+use std::path::PathBuf;
+
+use dragon_fnd::config::{Config, ConfigError};
+
+// A directory of its own per test, so tests can run in parallel
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dragon-fnd-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_reload_source_reads_only_its_files() {
+    let dir = scratch_dir("reload-source");
+    let (first, second) = (dir.join("first.toml"), dir.join("second.toml"));
+    std::fs::write(&first, "a = 1").unwrap();
+    std::fs::write(&second, "b = 1").unwrap();
+
+    let (watched, watcher) = Config::builder()
+        .with_file(&first, true)
+        .with_file(&second, true)
+        .with_env_vars("APP", "__", [("APP__C".to_string(), "1".to_string())])
+        .build_watched::<toml::Table>()
+        .unwrap();
+    // Stopped so that only `reload_source` rebuilds
+    drop(watcher);
+
+    std::fs::write(&first, "a = 2").unwrap();
+    std::fs::write(&second, "b = 2").unwrap();
+    watched.reload_source(0).unwrap();
+
+    let expected: toml::Table = toml::from_str("a = 2\nb = 1\nc = 1").unwrap();
+    assert_eq!(*watched.load(), expected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reload_source_counts_defaults_in_registration_order() {
+    let dir = scratch_dir("reload-defaults");
+    let (first, second) = (dir.join("first.toml"), dir.join("second.toml"));
+    std::fs::write(&first, "a = 1").unwrap();
+    std::fs::write(&second, "b = 1").unwrap();
+
+    let (watched, watcher) = Config::builder()
+        .with_file(&first, true) // source 0
+        .with_default("x", 1) // source 1
+        .with_file(&second, true) // source 2
+        .build_watched::<toml::Table>()
+        .unwrap();
+    drop(watcher);
+
+    std::fs::write(&first, "a = 2").unwrap();
+    std::fs::write(&second, "b = 2").unwrap();
+    watched.reload_source(0).unwrap();
+    let expected: toml::Table = toml::from_str("a = 2\nb = 1\nx = 1").unwrap();
+    assert_eq!(*watched.load(), expected);
+
+    assert!(matches!(
+        watched.reload_source(1),
+        Err(ConfigError::NotAFileSource(1))
+    ));
+
+    watched.reload_source(2).unwrap();
+    let expected: toml::Table = toml::from_str("a = 2\nb = 2\nx = 1").unwrap();
+    assert_eq!(*watched.load(), expected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "watch")]
+
+// The only test in its binary: it changes the process environment, which no
+// other test may observe
+
+use dragon_fnd::config::Config;
+
+const VAR: &str = "DRAGON_FND_WATCH_ENV_REGION";
+
+#[test]
+fn test_reloads_resolve_env_references_from_the_startup_snapshot() {
+    let dir = std::env::temp_dir().join(format!("dragon-fnd-watch-env-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("app.toml");
+    std::fs::write(&file, format!("region = \"${{env:{VAR}}}\"\nn = 1")).unwrap();
+    std::env::set_var(VAR, "eu");
+
+    let (watched, watcher) = Config::builder()
+        .with_file(&file, true)
+        .build_watched::<toml::Table>()
+        .unwrap();
+    drop(watcher);
+
+    std::env::set_var(VAR, "us");
+    std::fs::write(&file, format!("region = \"${{env:{VAR}}}\"\nn = 2")).unwrap();
+    watched.reload_source(0).unwrap();
+
+    let expected: toml::Table = toml::from_str("region = \"eu\"\nn = 2").unwrap();
+    assert_eq!(*watched.load(), expected);
+    std::env::remove_var(VAR);
+    std::fs::remove_dir_all(&dir).unwrap();
+}